@@ -1,15 +1,15 @@
-use std::io::{Read, Write};
-use std::net::TcpListener;
+use smn_shared::listen::{ListenAddr, Listener};
+use std::io::{self, Read, Write};
+use std::thread;
 
-fn main() {
+fn main() -> io::Result<()> {
     // User-controlled fields
     let port = "33033";
     let construction_title = "🚧 Under Construction";
     let construction_message = "Charmline coming soon.";
 
-    let bind_addr = format!("127.0.0.1:{}", port);
-    let listener = TcpListener::bind(&bind_addr).unwrap();
-    println!("Serving on http://{}", bind_addr);
+    let bind_addr: ListenAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let listeners = Listener::activated_or_bind(&[bind_addr])?;
 
     let html = format!(
         "HTTP/1.1 200 OK\r\n\
@@ -61,11 +61,43 @@ Content-Type: text/html; charset=utf-8\r\n\
         msg = construction_message
     );
 
-    for stream in listener.incoming() {
-        if let Ok(mut stream) = stream {
-            let mut buffer = [0_u8; 1024];
-            let _ = stream.read(&mut buffer);
-            let _ = stream.write_all(html.as_bytes());
+    thread::scope(|scope| {
+        for listener in &listeners {
+            println!("Serving on {}", listener);
+            scope.spawn(|| serve(listener, &html));
         }
+    });
+
+    Ok(())
+}
+
+fn serve(listener: &Listener, html: &str) {
+    match listener {
+        Listener::Tcp(listener) => listener.incoming().flatten().for_each(|stream| respond(stream, html)),
+        #[cfg(unix)]
+        Listener::Unix(listener) => listener.incoming().flatten().for_each(|stream| respond(stream, html)),
+    }
+}
+
+fn respond(mut stream: impl Read + Write, html: &str) {
+    let mut buffer = [0_u8; 1024];
+    let _ = stream.read(&mut buffer);
+
+    // Liveness and readiness probes; the page is static so both are always ok
+    let request = String::from_utf8_lossy(&buffer);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    if path == "/healthz" || path == "/readyz" {
+        let _ = stream.write_all(
+            b"HTTP/1.1 200 OK\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Cache-Control: no-store\r\n\
+Content-Length: 2\r\n\
+\r\n\
+ok",
+        );
+        return;
     }
+
+    let _ = stream.write_all(html.as_bytes());
 }
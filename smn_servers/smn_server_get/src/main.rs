@@ -1,17 +1,29 @@
 mod storage;
 mod server;
 
-use axum::{Router, extract::State, response::{Html, IntoResponse}, routing::get};
-use std::{error::Error, env, path::Path};
+use axum::{Router, extract::State, http::StatusCode, middleware, response::{Html, IntoResponse}, routing::get};
+use s3::Bucket;
+use std::{error::Error, env, path::Path, time::Duration};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, RwLock, atomic::{AtomicBool, Ordering}};
 
 use crate::storage::bucket::generate_bucket;
 use crate::storage::project_info::ProjectInfoConfig;
 use crate::storage::combined::CombinedProjectSet;
+use crate::server::core::ListenAddr;
+use crate::server::cors::{self, Cors, CorsConfig};
 use crate::server::html::render_index;
+use crate::server::rate_limit::{self, RateLimitRule, RateLimiter};
+use crate::server::security_headers::{self, SecurityConfig, SecurityHeaders, SecurityOverride, SecurityPolicy};
 
-#[derive(Clone)]
+/// Delay between bucket listing attempts while the server is not ready.
+const LISTING_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Default)]
 struct AppState {
-    combined: CombinedProjectSet,
+    combined: Arc<RwLock<CombinedProjectSet>>,
+    // Set once a bucket listing has succeeded
+    ready: Arc<AtomicBool>,
 }
 
 #[tokio::main]
@@ -29,18 +41,119 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let project_info =
         ProjectInfoConfig::load_from_primary_or_alt(primary_path, &alternate_path);
 
-    let combined = CombinedProjectSet::build(&bucket, &project_info).await;
+    let state = AppState::default();
+
+    // Serve even if the bucket is unreachable; /readyz reports it until a retry succeeds
+    if !load_listing(&state, &bucket, &project_info).await {
+        tokio::spawn(retry_listing(state.clone(), bucket, project_info));
+    }
+
+    // nginx proxies from the same host, so only its forwarding headers are trusted
+    let limiter = RateLimiter::new(
+        vec![RateLimitRule {
+            path_prefix: "/".to_string(),
+            burst: 60,
+            per_second: 10.0,
+        }],
+        vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)],
+    );
+
+    // Tool pages on the main site fetch from here
+    let cors_policy = Cors::new(CorsConfig {
+        allowed_origins: vec![
+            "http://localhost:33030".to_string(),
+            "http://127.0.0.1:33030".to_string(),
+        ],
+        allowed_methods: vec!["GET".to_string()],
+        allowed_headers: vec!["Content-Type".to_string()],
+        allow_credentials: false,
+        max_age: 600,
+    })
+    .unwrap_or_else(|e| panic!("CORS: {}", e));
+
+    // The index page inlines its styles and loads Google Fonts
+    let page_policy = SecurityPolicy {
+        content_security_policy: Some(
+            "default-src 'self'; style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; \
+font-src https://fonts.gstatic.com; base-uri 'self'; form-action 'self'; frame-ancestors 'none'"
+                .to_string(),
+        ),
+        ..SecurityPolicy::default()
+    };
+    // Probes are plain text for the stack tooling, so they skip the page policy
+    let probe_policy = SecurityPolicy {
+        content_security_policy: None,
+        frame_options: None,
+        permissions_policy: None,
+        ..page_policy.clone()
+    };
+    let security = SecurityHeaders::new(SecurityConfig {
+        policy: page_policy,
+        overrides: ["/healthz", "/readyz"]
+            .into_iter()
+            .map(|path| SecurityOverride {
+                path_prefix: path.to_string(),
+                policy: probe_policy.clone(),
+            })
+            .collect(),
+    });
 
     let app = Router::new()
         .route("/", get(index))
-        .with_state(AppState {
-            combined,
-        });
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+        .layer(middleware::from_fn_with_state(limiter, rate_limit::limit))
+        .layer(middleware::from_fn_with_state(cors_policy, cors::cors))
+        .layer(middleware::from_fn_with_state(security, security_headers::apply));
+
+    // e.g. SMN_LISTEN="127.0.0.1:33031, unix:/run/smn/get.sock?mode=660"
+    let listen = match env::var("SMN_LISTEN") {
+        Ok(listen) => listen.split(',').map(str::parse).collect::<Result<Vec<ListenAddr>, _>>()?,
+        Err(_) => vec![ListenAddr::Tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 33031)))],
+    };
 
-    server::core::serve(app, "33031".to_string()).await?;
+    server::core::serve(app, listen).await?;
     Ok(())
 }
 
+async fn load_listing(state: &AppState, bucket: &Bucket, info: &ProjectInfoConfig) -> bool {
+    match CombinedProjectSet::build(bucket, info).await {
+        Ok(combined) => {
+            *state.combined.write().unwrap() = combined;
+            state.ready.store(true, Ordering::Release);
+            true
+        }
+        Err(e) => {
+            eprintln!("Bucket listing failed: {}", e);
+            false
+        }
+    }
+}
+
+async fn retry_listing(state: AppState, bucket: Bucket, info: ProjectInfoConfig) {
+    loop {
+        tokio::time::sleep(LISTING_RETRY_DELAY).await;
+
+        if load_listing(&state, &bucket, &info).await {
+            println!("Bucket listing succeeded, server is ready");
+            return;
+        }
+    }
+}
+
 async fn index(State(state): State<AppState>) -> impl IntoResponse {
-    Html(render_index(&state.combined))
+    Html(render_index(&state.combined.read().unwrap()))
+}
+
+async fn healthz() -> impl IntoResponse {
+    "ok"
+}
+
+async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    if state.ready.load(Ordering::Acquire) {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "bucket listing unavailable")
+    }
 }
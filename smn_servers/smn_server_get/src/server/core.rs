@@ -1,12 +1,43 @@
-use axum::Router;
-use std::{error::Error, net::SocketAddr};
+use axum::{Extension, Router, extract::ConnectInfo};
+use std::{error::Error, net::{Ipv4Addr, SocketAddr}};
+use tokio::task::JoinSet;
 
-pub async fn serve(app: Router, port: String) -> Result<(), Box<dyn Error>> {
-    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse()?;
-    println!("Listening on http://{}", addr);
+pub use smn_shared::listen::{ListenAddr, Listener};
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app.into_make_service()).await?;
+/// Serves `app` on every address at once, returning when any of them fails. When the
+/// service manager passed in sockets (`LISTEN_PID`/`LISTEN_FDS`), those are served
+/// instead, so restarts never close the listening socket.
+pub async fn serve(app: Router, listen: Vec<ListenAddr>) -> Result<(), Box<dyn Error>> {
+    let listeners = Listener::activated_or_bind(&listen)?;
+
+    let mut servers = JoinSet::new();
+
+    for listener in listeners {
+        println!("Listening on {}", listener);
+
+        match listener {
+            Listener::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                let service = app.clone().into_make_service_with_connect_info::<SocketAddr>();
+                servers.spawn(async move { axum::serve(listener, service).await });
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                let listener = tokio::net::UnixListener::from_std(listener)?;
+
+                // Only local processes can open the socket, so they count as loopback peers
+                let local = ConnectInfo(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)));
+                let service = app.clone().layer(Extension(local)).into_make_service();
+                servers.spawn(async move { axum::serve(listener, service).await });
+            }
+        }
+    }
+
+    if let Some(result) = servers.join_next().await {
+        result??;
+    }
 
     Ok(())
 }
@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+        header::{ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use smn_shared::cors::{CorsPolicy, PREFLIGHT_VARY};
+use std::sync::Arc;
+
+pub use smn_shared::cors::CorsConfig;
+
+/// Answers `OPTIONS` preflights and adds `Access-Control-*` headers for allowed origins.
+#[derive(Clone)]
+pub struct Cors {
+    policy: Arc<CorsPolicy>,
+}
+
+impl Cors {
+    pub fn new(config: CorsConfig) -> Result<Self, String> {
+        CorsPolicy::new(config).map(|policy| Self {
+            policy: Arc::new(policy),
+        })
+    }
+}
+
+/// Middleware answering preflights itself and decorating every other cross-origin response.
+pub async fn cors(State(cors): State<Cors>, req: Request, next: Next) -> Response {
+    let Some(origin) = req.headers().get(ORIGIN).and_then(|value| value.to_str().ok()).map(str::to_string)
+    else {
+        return next.run(req).await;
+    };
+
+    if req.method() == Method::OPTIONS && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+        let header = |name| req.headers().get(name).and_then(|value: &HeaderValue| value.to_str().ok());
+        let allowed = cors.policy.preflight_headers(
+            &origin,
+            header(ACCESS_CONTROL_REQUEST_METHOD).unwrap_or(""),
+            header(ACCESS_CONTROL_REQUEST_HEADERS).unwrap_or(""),
+        );
+
+        let mut resp = StatusCode::NO_CONTENT.into_response();
+        resp.headers_mut().append(VARY, HeaderValue::from_static(PREFLIGHT_VARY));
+        insert_all(resp.headers_mut(), allowed.unwrap_or_default());
+        return resp;
+    }
+
+    let mut resp = next.run(req).await;
+    resp.headers_mut().append(VARY, HeaderValue::from_static("Origin"));
+    insert_all(resp.headers_mut(), cors.policy.response_headers(&origin).unwrap_or_default());
+    resp
+}
+
+fn insert_all(headers: &mut HeaderMap, values: Vec<(&'static str, String)>) {
+    for (name, value) in values {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(&value)) {
+            headers.insert(name, value);
+        }
+    }
+}
@@ -272,9 +272,7 @@ details.group {
     .to_string()
 }
 
-fn categorize_projects<'a>(
-    set: &'a CombinedProjectSet,
-) -> BTreeMap<String, Vec<&'a CombinedProject>> {
+fn categorize_projects(set: &CombinedProjectSet) -> BTreeMap<String, Vec<&CombinedProject>> {
     let mut map: BTreeMap<String, Vec<&CombinedProject>> = BTreeMap::new();
 
     for proj in &set.projects {
@@ -312,12 +310,12 @@ fn render_project(proj: &CombinedProject) -> String {
         out.push_str("<div class=\"space-01\"> </div>");
 
         for f in &files {
-            let filename = f.relative.split('/').last().unwrap_or(&f.relative);
+            let filename = f.relative.split('/').next_back().unwrap_or(&f.relative);
 
             if let Some(log) = proj
                 .version_changelog
                 .iter()
-                .find(|v| v.version.split('/').last().unwrap_or(&v.version) == filename)
+                .find(|v| v.version.split('/').next_back().unwrap_or(&v.version) == filename)
             {
                 out.push_str(&render_version_block(log, f));
             } else {
@@ -360,7 +358,7 @@ fn render_version_block(v: &CombinedVersion, f: &DiscoveredFile) -> String {
     out.push_str(r#"<div class="version-row">"#);
 
     // LEFT COLUMN → the FILE LINK ONLY
-    let link_visual_text = f.relative.split('/').last().unwrap_or(&f.relative);
+    let link_visual_text = f.relative.split('/').next_back().unwrap_or(&f.relative);
 
     out.push_str(&format!(
         r#"<div class="version-left"><a href="{0}" class="version-link">{1}</a></div>"#,
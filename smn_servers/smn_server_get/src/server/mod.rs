@@ -1,2 +1,5 @@
 pub mod core;
-pub mod html;
\ No newline at end of file
+pub mod cors;
+pub mod html;
+pub mod rate_limit;
+pub mod security_headers;
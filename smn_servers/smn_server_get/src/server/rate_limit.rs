@@ -0,0 +1,74 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use smn_shared::rate_limit::TokenBuckets;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+pub use smn_shared::rate_limit::RateLimitRule;
+
+/// Token-bucket limiter keyed by rule and client address.
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<TokenBuckets>,
+    trusted_proxies: Arc<Vec<IpAddr>>,
+}
+
+impl RateLimiter {
+    /// The longest matching prefix applies; paths matching no rule are unlimited.
+    pub fn new(rules: Vec<RateLimitRule>, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            buckets: Arc::new(TokenBuckets::new(rules)),
+            trusted_proxies: Arc::new(trusted_proxies),
+        }
+    }
+
+    /// Forwarding headers are only believed from trusted proxies, walking
+    /// `X-Forwarded-For` right to left past any further trusted hops.
+    fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+        if let Some(chain) = header("x-forwarded-for") {
+            for hop in chain.rsplit(',') {
+                match hop.trim().parse::<IpAddr>() {
+                    Ok(ip) if self.trusted_proxies.contains(&ip) => continue,
+                    Ok(ip) => return ip,
+                    Err(_) => break,
+                }
+            }
+        }
+
+        header("x-real-ip")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(peer)
+    }
+}
+
+/// Middleware answering `429 Too Many Requests` once a client's bucket is empty.
+pub async fn limit(
+    State(limiter): State<RateLimiter>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client = limiter.client_ip(peer.ip(), req.headers());
+
+    match limiter.buckets.take(req.uri().path(), client) {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => {
+            let mut resp = (StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response();
+            resp.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+            resp
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+pub use smn_shared::security_headers::{SecurityConfig, SecurityOverride, SecurityPolicy};
+
+/// Security-header policy applied to every response; headers a handler set itself are kept.
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    config: Arc<SecurityConfig>,
+}
+
+impl SecurityHeaders {
+    pub fn new(config: SecurityConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+}
+
+/// Middleware adding the policy for the request path to the response.
+pub async fn apply(State(security): State<SecurityHeaders>, req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    let mut resp = next.run(req).await;
+    let headers = resp.headers_mut();
+
+    // Only reachable through nginx, which terminates TLS
+    for (name, value) in security.config.policy_for(&path).headers(true) {
+        let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+
+        if !headers.contains_key(&name)
+            && let Ok(value) = HeaderValue::from_str(&value)
+        {
+            headers.insert(name, value);
+        }
+    }
+
+    resp
+}
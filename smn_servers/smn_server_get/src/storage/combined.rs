@@ -1,9 +1,9 @@
 use serde::Serialize;
-use s3::Bucket;
+use s3::{Bucket, error::S3Error};
 
 use crate::storage::project_info::ProjectInfoConfig;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CombinedProjectSet {
     pub projects: Vec<CombinedProject>,
 }
@@ -34,10 +34,10 @@ pub struct DiscoveredFile {
 }
 
 impl CombinedProjectSet {
-    pub async fn build(bucket: &Bucket, info: &ProjectInfoConfig) -> Self {
+    pub async fn build(bucket: &Bucket, info: &ProjectInfoConfig) -> Result<Self, S3Error> {
         // 1. Pull all keys from bucket
         let prefix = "smn-get/";
-        let list = bucket.list(prefix.to_string(), None).await.unwrap();
+        let list = bucket.list(prefix.to_string(), None).await?;
 
         let mut all_files: Vec<DiscoveredFile> = Vec::new();
 
@@ -114,6 +114,6 @@ impl CombinedProjectSet {
             }
         }
 
-        Self { projects }
+        Ok(Self { projects })
     }
 }
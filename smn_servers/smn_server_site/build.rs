@@ -0,0 +1,65 @@
+//! With the `embed` feature, compiles the static tree into the binary as a table of
+//! `(path, contents, modified)` entries that seeds `FileCache`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+fn main() {
+    if env::var_os("CARGO_FEATURE_EMBED").is_none() {
+        return;
+    }
+
+    // Defaults to ./static, the directory the server reads at runtime
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let root = match env::var("SMN_EMBED_DIR") {
+        Ok(dir) => manifest_dir.join(dir),
+        Err(_) => manifest_dir.join("static"),
+    };
+    println!("cargo:rerun-if-env-changed=SMN_EMBED_DIR");
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let mut entries = Vec::new();
+    collect(&root, "", &mut entries);
+    entries.sort();
+
+    let mut out = String::from("pub static EMBEDDED: &[(&str, &str, u64)] = &[\n");
+    for (key, path, modified) in entries {
+        out.push_str(&format!(
+            "    ({:?}, include_str!({:?}), {}),\n",
+            key,
+            path.display().to_string(),
+            modified
+        ));
+    }
+    out.push_str("];\n");
+
+    let dest = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded_static.rs");
+    fs::write(dest, out).unwrap();
+}
+
+/// Text files only, matching what `FileCache` loads from disk.
+fn collect(dir: &Path, prefix: &str, entries: &mut Vec<(String, PathBuf, u64)>) {
+    let Ok(read) = fs::read_dir(dir) else {
+        println!("cargo:warning=embed: cannot read {}", dir.display());
+        return;
+    };
+
+    for entry in read.flatten() {
+        let path = entry.path();
+        let key = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+
+        if path.is_dir() {
+            collect(&path, &key, entries);
+        } else if path.is_file() && fs::read_to_string(&path).is_ok() {
+            let modified = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_secs());
+            entries.push((key, path.canonicalize().unwrap_or(path), modified));
+        }
+    }
+}
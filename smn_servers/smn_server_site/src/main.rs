@@ -1,21 +1,209 @@
 mod structs;
 mod server;
+#[cfg(feature = "async")]
+mod server_async;
+mod middleware;
 mod plugins;
+#[cfg(test)]
+mod test_support;
+
+use std::env;
+use std::time::Duration;
 
 use server::Server;
+use crate::middleware::middleware_auth::{AuthConfig, AuthRule, MiddlewareAuth};
+use crate::middleware::middleware_cors::{CorsConfig, MiddlewareCors};
+use crate::middleware::middleware_security_headers::{
+    MiddlewareSecurityHeaders, SecurityConfig, SecurityOverride, SecurityPolicy,
+};
+use crate::middleware::middleware_session::{MiddlewareSession, SessionConfig, SessionStorage};
 use crate::plugins::{
-    plugin_helloworld::PluginHelloWorld,
-    plugin_static_files::PluginStaticFile
+    plugin_blog::{BlogConfig, PluginBlog},
+    plugin_contact_form::{ContactFormConfig, PluginContactForm},
+    plugin_echo_socket::PluginEchoSocket,
+    plugin_helloworld::{HelloWorldSettings, PluginHelloWorld},
+    plugin_sitemap::{PluginSitemap, SitemapConfig},
+    plugin_static_files::{CacheMatch, CacheRule, LanguageSettings, PluginStaticFile, StaticFileSettings},
+    plugin_upload::{PluginUpload, UploadConfig},
 };
-use crate::structs::plugin::Plugin;
+#[cfg(feature = "async")]
+use crate::plugins::plugin_get_status::PluginGetStatus;
+use crate::structs::config::ServerConfig;
+use crate::structs::cookie::SameSite;
+use crate::structs::middleware::Middleware;
+use crate::structs::rate_limit::RateLimitRule;
+use crate::structs::plugin::PluginEntry;
 
 fn main() {
-    let plugins: Vec<Box<dyn Plugin>> = vec![
-        Box::new(PluginStaticFile),
-        Box::new(PluginHelloWorld),
+    // CORS goes first so preflights are answered without credentials
+    let mut middlewares: Vec<Box<dyn Middleware>> = vec![
+        Box::new(MiddlewareCors::new(CorsConfig {
+            allowed_origins: vec![
+                "http://localhost:33031".to_string(),
+                "http://127.0.0.1:33031".to_string(),
+            ],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            allowed_headers: vec!["Authorization".to_string(), "Content-Type".to_string()],
+            allow_credentials: true,
+            max_age: 600,
+        })
+        .unwrap_or_else(|e| panic!("CORS: {}", e))),
+        Box::new(MiddlewareSecurityHeaders::new(SecurityConfig {
+            policy: SecurityPolicy::default(),
+            // Tool pages trial a stricter script policy before it is enforced
+            overrides: vec![SecurityOverride {
+                path_prefix: "/tools/".to_string(),
+                policy: SecurityPolicy {
+                    content_security_policy: Some(
+                        "default-src 'self'; script-src 'self'; object-src 'none'; base-uri 'none'; frame-ancestors 'none'"
+                            .to_string(),
+                    ),
+                    csp_report_only: true,
+                    ..SecurityPolicy::default()
+                },
+            }],
+        })),
+        Box::new(MiddlewareAuth::new(AuthConfig {
+            credentials_file: "./config/auth_credentials.txt".to_string(),
+            tokens_file: "./config/auth_tokens.txt".to_string(),
+            rules: vec![
+                AuthRule {
+                    path_prefix: "/drafts/".to_string(),
+                    realm: "Drafts".to_string(),
+                    basic: true,
+                    bearer: false,
+                },
+                AuthRule {
+                    path_prefix: "/upload".to_string(),
+                    realm: "Uploads".to_string(),
+                    basic: true,
+                    bearer: true,
+                },
+                AuthRule {
+                    path_prefix: "/admin/".to_string(),
+                    realm: "Admin".to_string(),
+                    basic: true,
+                    bearer: true,
+                },
+            ],
+        })),
+    ];
+
+    // Sessions need a stable signing key, so they stay off unless one is provided
+    if let Ok(secret) = env::var("SMN_SESSION_SECRET") {
+        middlewares.push(Box::new(MiddlewareSession::new(SessionConfig {
+            cookie_name: "smn_session".to_string(),
+            secret: secret.into_bytes(),
+            max_age: Duration::from_secs(14 * 24 * 60 * 60),
+            same_site: SameSite::Lax,
+            // Kept in memory unless a directory is given to survive restarts
+            storage: match env::var("SMN_SESSION_DIR") {
+                Ok(dir) => SessionStorage::Disk { dir },
+                Err(_) => SessionStorage::Memory,
+            },
+        })));
+    }
+
+    let plugins: Vec<PluginEntry> = vec![
+        // Ahead of StaticFile so a stray robots.txt in file_root does not shadow it
+        PluginSitemap::new(SitemapConfig {
+            exclude: vec![
+                "/contact-thanks.html".to_string(),
+                "/drafts/**".to_string(),
+                "/admin/**".to_string(),
+            ],
+            disallow: vec!["/admin/".to_string(), "/drafts/".to_string(), "/upload".to_string()],
+        })
+        .into(),
+        PluginStaticFile.into(),
+        PluginBlog::new(BlogConfig {
+            posts_dir: "./posts".to_string(),
+            mount: "/blog".to_string(),
+            title: "Devlog".to_string(),
+            description: "Development notes from SummonTheCat".to_string(),
+            author: "SummonTheCat".to_string(),
+            posts_per_page: 10,
+            feed_size: 20,
+        })
+        .into(),
+        PluginEchoSocket.into(),
+        PluginContactForm::new(ContactFormConfig {
+            path: "/contact/submit".to_string(),
+            store_path: "./data/contact_submissions.jsonl".to_string(),
+            thank_you_path: "/contact-thanks.html".to_string(),
+            honeypot_field: "website".to_string(),
+        })
+        .into(),
+        PluginUpload::new(UploadConfig {
+            path: "/upload".to_string(),
+            upload_dir: "./data/uploads".to_string(),
+        })
+        .into(),
+        #[cfg(feature = "async")]
+        PluginEntry::from_async(PluginGetStatus),
+        PluginHelloWorld.into(),
     ];
 
-    let server = Server::new("33030", plugins, "./static");
+    let mut config = ServerConfig {
+        dev_mode: env::args().any(|arg| arg == "--dev"),
+        ..ServerConfig::default()
+    };
+
+    // e.g. SMN_LISTEN="127.0.0.1:33030, unix:/run/smn/site.sock?mode=660"
+    if let Ok(listen) = env::var("SMN_LISTEN") {
+        config.listen = listen
+            .split(',')
+            .map(|addr| addr.parse().unwrap_or_else(|e| panic!("SMN_LISTEN: {}", e)))
+            .collect();
+    }
+
+    // Keep authored formatting while developing so pages are easy to inspect
+    config.assets.minify = !config.dev_mode;
+
+    // A handful of contact submissions per client, then one every ten minutes
+    config.rate_limit.rules.push(RateLimitRule {
+        path_prefix: "/contact/submit".to_string(),
+        burst: 3,
+        per_second: 1.0 / 600.0,
+    });
+
+    config.plugin_settings.insert(
+        "HelloWorld",
+        HelloWorldSettings {
+            heading: "Hello World Plugin".to_string(),
+        },
+    );
+
+    config.plugin_settings.insert(
+        "StaticFile",
+        StaticFileSettings {
+            cache_rules: vec![
+                CacheRule {
+                    matcher: CacheMatch::Glob("/assets/**".to_string()),
+                    cache_control: "public, max-age=31536000, immutable".to_string(),
+                    expires_in: Some(Duration::from_secs(31_536_000)),
+                },
+                CacheRule {
+                    matcher: CacheMatch::Extension("html".to_string()),
+                    cache_control: "no-cache".to_string(),
+                    expires_in: None,
+                },
+                CacheRule {
+                    matcher: CacheMatch::Glob("/**/*.{css,js}".to_string()),
+                    cache_control: "public, max-age=3600".to_string(),
+                    expires_in: None,
+                },
+            ],
+            // Translations live beside the English page, e.g. contact.fr.html
+            languages: Some(LanguageSettings {
+                default: "en".to_string(),
+                translations: vec!["fr".to_string()],
+                cookie_name: "lang".to_string(),
+            }),
+        },
+    );
+
+    let server = Server::new(config, middlewares, plugins);
 
     server.run();
 }
@@ -0,0 +1,341 @@
+use crate::structs::core::{Request, Response};
+use crate::structs::language::split_language_prefix;
+use crate::structs::middleware::Middleware;
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Mutex;
+
+/// Successful Basic logins remembered so argon2/bcrypt only runs once per password.
+const VERIFIED_CACHE_LIMIT: usize = 1024;
+
+/// Verified against when no credentials are configured, so an unknown user still costs
+/// an argon2 run. The password behind it is irrelevant; the result is always discarded.
+const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$c21uLWF1dGgtZHVtbXk$X8B3R236arCwdxoPKKMy4cPWjYu0FooP/NLl4/5cFeQ";
+
+pub struct AuthRule {
+    pub path_prefix: String,
+    pub realm: String,
+    /// Accept HTTP Basic against the credentials file.
+    pub basic: bool,
+    /// Accept `Authorization: Bearer` tokens from the tokens file.
+    pub bearer: bool,
+}
+
+pub struct AuthConfig {
+    /// `user:hash` per line, hashes in argon2 PHC or bcrypt (`htpasswd -B`) form.
+    pub credentials_file: String,
+    /// `label:sha256-hex-of-token` per line.
+    pub tokens_file: String,
+    /// The longest matching prefix applies; unmatched paths are public. Translations
+    /// served under a language prefix such as `/fr/drafts/` fall under the rule for the
+    /// untranslated path too, so rules should name untranslated prefixes.
+    pub rules: Vec<AuthRule>,
+}
+
+/// Guards path prefixes behind HTTP Basic and/or bearer tokens.
+pub struct MiddlewareAuth {
+    rules: Vec<AuthRule>,
+    credentials: HashMap<String, String>,
+    /// Checked for unknown users so they take as long as known ones with a wrong password.
+    dummy_hash: String,
+    token_hashes: HashSet<String>,
+    verified: Mutex<HashSet<[u8; 32]>>,
+}
+
+impl MiddlewareAuth {
+    pub fn new(config: AuthConfig) -> Self {
+        let credentials: HashMap<String, String> = read_pairs(&config.credentials_file)
+            .into_iter()
+            .collect();
+
+        let token_hashes: HashSet<String> = read_pairs(&config.tokens_file)
+            .into_iter()
+            .map(|(_, hash)| hash.to_ascii_lowercase())
+            .collect();
+
+        println!(
+            "Auth: {} user(s), {} token(s), {} protected prefix(es)",
+            credentials.len(),
+            token_hashes.len(),
+            config.rules.len()
+        );
+
+        // A configured hash has the same algorithm and cost as a real login
+        let dummy_hash = credentials
+            .values()
+            .min()
+            .cloned()
+            .unwrap_or_else(|| DUMMY_HASH.to_string());
+
+        Self {
+            rules: config.rules,
+            credentials,
+            dummy_hash,
+            token_hashes,
+            verified: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn rule_for(&self, path: &str) -> Option<&AuthRule> {
+        self.rules
+            .iter()
+            .filter(|rule| path.starts_with(&rule.path_prefix))
+            .max_by_key(|rule| rule.path_prefix.len())
+    }
+
+    /// `/fr/drafts/x.html` may be served from `/drafts/x.fr.html`, so a language prefix
+    /// never gets a path out from under the rule that guards it without one.
+    fn rule_for_request(&self, req: &Request) -> Option<&AuthRule> {
+        let untranslated = split_language_prefix(&req.path).and_then(|(_, rest)| self.rule_for(rest));
+
+        [self.rule_for(&req.path), untranslated]
+            .into_iter()
+            .flatten()
+            .max_by_key(|rule| rule.path_prefix.len())
+    }
+
+    fn authorized(&self, rule: &AuthRule, req: &Request) -> bool {
+        let Some((scheme, value)) = req
+            .header("Authorization")
+            .and_then(|header| header.trim().split_once(' '))
+        else {
+            return false;
+        };
+
+        if rule.basic && scheme.eq_ignore_ascii_case("Basic") {
+            return self.check_basic(value.trim());
+        }
+
+        if rule.bearer && scheme.eq_ignore_ascii_case("Bearer") {
+            // Tokens are high-entropy, so comparing their digests leaks nothing useful
+            return self.token_hashes.contains(&sha256_hex(value.trim().as_bytes()));
+        }
+
+        false
+    }
+
+    fn check_basic(&self, encoded: &str) -> bool {
+        let Some(decoded) = STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+        else {
+            return false;
+        };
+
+        let Some((user, password)) = decoded.split_once(':') else {
+            return false;
+        };
+
+        let Some(hash) = self.credentials.get(user) else {
+            // Answering an unknown user straight away would reveal which users exist
+            verify_password(password, &self.dummy_hash);
+            return false;
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(user.as_bytes());
+        hasher.update([0]);
+        hasher.update(password.as_bytes());
+        hasher.update([0]);
+        hasher.update(hash.as_bytes());
+        let key: [u8; 32] = hasher.finalize().into();
+
+        if self.verified.lock().unwrap().contains(&key) {
+            return true;
+        }
+
+        if !verify_password(password, hash) {
+            return false;
+        }
+
+        let mut verified = self.verified.lock().unwrap();
+        if verified.len() >= VERIFIED_CACHE_LIMIT {
+            verified.clear();
+        }
+        verified.insert(key);
+        true
+    }
+}
+
+impl Middleware for MiddlewareAuth {
+    fn middleware_name(&self) -> &str {
+        "Auth"
+    }
+
+    fn middleware_before(&self, req: &Request) -> Option<Response> {
+        let rule = self.rule_for_request(req)?;
+
+        if self.authorized(rule, req) {
+            return None;
+        }
+
+        let mut challenges = Vec::new();
+        if rule.basic {
+            challenges.push(format!("Basic realm=\"{}\", charset=\"UTF-8\"", rule.realm));
+        }
+        if rule.bearer {
+            challenges.push(format!("Bearer realm=\"{}\"", rule.realm));
+        }
+
+        Some(
+            Response::new(401)
+                .with_header("WWW-Authenticate", &challenges.join(", "))
+                .with_header("Content-Type", "text/plain; charset=utf-8")
+                .with_header("Cache-Control", "no-store")
+                .with_body("Authentication required"),
+        )
+    }
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    if hash.starts_with("$argon2") {
+        return PasswordHash::new(hash)
+            .and_then(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed))
+            .is_ok();
+    }
+
+    if hash.starts_with("$2") {
+        return bcrypt::verify(password, hash).unwrap_or(false);
+    }
+
+    false
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Reads `key:value` lines, skipping blanks and `#` comments. A missing file yields nothing,
+/// which leaves the protected prefixes locked rather than open.
+fn read_pairs(path: &str) -> Vec<(String, String)> {
+    let Ok(raw) = fs::read_to_string(path) else {
+        println!("Auth: could not read {}", path);
+        return Vec::new();
+    };
+
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{RequestBuilder, ScratchDir};
+    use argon2::{Algorithm, Params, PasswordHasher, Version, password_hash::SaltString};
+
+    const TOKEN: &str = "tok_4f1c9a7e2b";
+
+    /// `ada` has an argon2 hash and `grace` a bcrypt one, both at minimal cost.
+    fn auth_with_credentials(name: &str) -> MiddlewareAuth {
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(8, 1, 1, None).unwrap())
+            .hash_password(b"lovelace", &SaltString::from_b64("c21uLWF1dGgtdGVzdA").unwrap())
+            .unwrap()
+            .to_string();
+        let bcrypt = bcrypt::hash("hopper", 4).unwrap();
+
+        // Only read while the middleware is built
+        let dir = ScratchDir::new(&format!("auth-{}", name));
+        let credentials = dir.join("credentials");
+        let tokens = dir.join("tokens");
+        fs::write(&credentials, format!("# users\nada:{}\n\ngrace:{}\n", argon2, bcrypt)).unwrap();
+        fs::write(&tokens, format!("deploy:{}\n", sha256_hex(TOKEN.as_bytes()))).unwrap();
+
+        MiddlewareAuth::new(AuthConfig {
+            credentials_file: credentials.to_string_lossy().to_string(),
+            tokens_file: tokens.to_string_lossy().to_string(),
+            rules: vec![
+                AuthRule {
+                    path_prefix: "/drafts/".to_string(),
+                    realm: "Drafts".to_string(),
+                    basic: true,
+                    bearer: false,
+                },
+                AuthRule {
+                    path_prefix: "/api/".to_string(),
+                    realm: "API".to_string(),
+                    basic: false,
+                    bearer: true,
+                },
+            ],
+        })
+    }
+
+    fn auth() -> MiddlewareAuth {
+        MiddlewareAuth::new(AuthConfig {
+            credentials_file: String::new(),
+            tokens_file: String::new(),
+            rules: vec![AuthRule {
+                path_prefix: "/drafts/".to_string(),
+                realm: "Drafts".to_string(),
+                basic: true,
+                bearer: false,
+            }],
+        })
+    }
+
+    fn status(auth: &MiddlewareAuth, path: &str, authorization: Option<&str>) -> Option<u16> {
+        let mut req = RequestBuilder::get(path);
+        if let Some(value) = authorization {
+            req = req.header("Authorization", value);
+        }
+        auth.middleware_before(&req.build()).map(|resp| resp.status)
+    }
+
+    fn basic(user: &str, password: &str) -> String {
+        format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)))
+    }
+
+    #[test]
+    fn basic_credentials_verify_against_argon2_and_bcrypt() {
+        let auth = auth_with_credentials("basic");
+
+        assert_eq!(status(&auth, "/drafts/a.html", Some(&basic("ada", "lovelace"))), None);
+        assert_eq!(status(&auth, "/drafts/a.html", Some(&basic("grace", "hopper"))), None);
+        // Served from the verified cache the second time
+        assert_eq!(status(&auth, "/drafts/a.html", Some(&basic("grace", "hopper"))), None);
+
+        assert_eq!(status(&auth, "/drafts/a.html", Some(&basic("ada", "hopper"))), Some(401));
+        assert_eq!(status(&auth, "/drafts/a.html", Some(&basic("linus", "lovelace"))), Some(401));
+        assert_eq!(status(&auth, "/drafts/a.html", Some("Basic not-base64!")), Some(401));
+        assert_eq!(status(&auth, "/drafts/a.html", None), Some(401));
+    }
+
+    #[test]
+    fn bearer_tokens_verify_by_digest() {
+        let auth = auth_with_credentials("bearer");
+
+        assert_eq!(status(&auth, "/api/stats", Some(&format!("Bearer {}", TOKEN))), None);
+        assert_eq!(status(&auth, "/api/stats", Some("Bearer tok_unknown")), Some(401));
+        // Each rule only accepts the schemes it names
+        assert_eq!(status(&auth, "/api/stats", Some(&basic("ada", "lovelace"))), Some(401));
+        assert_eq!(status(&auth, "/drafts/a.html", Some(&format!("Bearer {}", TOKEN))), Some(401));
+    }
+
+    #[test]
+    fn unknown_users_are_checked_against_a_configured_hash() {
+        assert!(auth_with_credentials("dummy").dummy_hash.starts_with("$2"));
+        assert_eq!(auth().dummy_hash, DUMMY_HASH);
+        assert!(PasswordHash::new(DUMMY_HASH).is_ok());
+    }
+
+    #[test]
+    fn language_prefix_does_not_escape_a_rule() {
+        let auth = auth();
+
+        assert_eq!(status(&auth, "/drafts/post.html", None), Some(401));
+        assert_eq!(status(&auth, "/fr/drafts/post.html", None), Some(401));
+        assert_eq!(status(&auth, "/fr/post.html", None), None);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::structs::core::{Request, Response};
+use crate::structs::middleware::Middleware;
+use smn_shared::cors::{CorsPolicy, PREFLIGHT_VARY};
+
+pub use smn_shared::cors::CorsConfig;
+
+/// Answers `OPTIONS` preflights and adds `Access-Control-*` headers for allowed origins.
+pub struct MiddlewareCors {
+    policy: CorsPolicy,
+}
+
+impl MiddlewareCors {
+    pub fn new(config: CorsConfig) -> Result<Self, String> {
+        CorsPolicy::new(config).map(|policy| Self { policy })
+    }
+}
+
+impl Middleware for MiddlewareCors {
+    fn middleware_name(&self) -> &str {
+        "Cors"
+    }
+
+    fn middleware_before(&self, req: &Request) -> Option<Response> {
+        let origin = req.header("Origin")?;
+
+        if !is_preflight(req) {
+            return None;
+        }
+
+        let mut resp = Response::new(204);
+        add_vary(&mut resp, PREFLIGHT_VARY);
+
+        let method = req.header("Access-Control-Request-Method").unwrap_or("");
+        let headers = req.header("Access-Control-Request-Headers").unwrap_or("");
+        for (name, value) in self.policy.preflight_headers(origin, method, headers).unwrap_or_default() {
+            resp.set_header(name, &value);
+        }
+
+        Some(resp)
+    }
+
+    fn middleware_after(&self, req: &Request, resp: &mut Response) {
+        // Preflights were finished in `middleware_before`
+        let Some(origin) = req.header("Origin").filter(|_| !is_preflight(req)) else {
+            return;
+        };
+
+        add_vary(resp, "Origin");
+
+        for (name, value) in self.policy.response_headers(origin).unwrap_or_default() {
+            resp.set_header(name, &value);
+        }
+    }
+}
+
+fn is_preflight(req: &Request) -> bool {
+    req.method == "OPTIONS" && req.header("Access-Control-Request-Method").is_some()
+}
+
+/// Merges names into `Vary` without repeating any already listed.
+fn add_vary(resp: &mut Response, names: &str) {
+    let mut vary: Vec<String> = resp
+        .header("Vary")
+        .map(|existing| existing.split(',').map(|name| name.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    for name in names.split(',').map(str::trim) {
+        if !vary.iter().any(|existing| existing.eq_ignore_ascii_case(name)) {
+            vary.push(name.to_string());
+        }
+    }
+
+    resp.set_header("Vary", &vary.join(", "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RequestBuilder;
+
+    fn cors() -> MiddlewareCors {
+        MiddlewareCors::new(CorsConfig {
+            allowed_origins: vec!["https://app.test".to_string()],
+            allowed_methods: vec!["GET".to_string()],
+            allowed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: 600,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn preflights_are_answered_and_vary_is_merged() {
+        let preflight = RequestBuilder::new("OPTIONS", "/api")
+            .header("Origin", "https://app.test")
+            .header("Access-Control-Request-Method", "GET")
+            .build();
+        let resp = cors().middleware_before(&preflight).unwrap();
+        assert_eq!(resp.status, 204);
+        assert_eq!(resp.header("Access-Control-Allow-Origin"), Some("https://app.test"));
+
+        let req = RequestBuilder::get("/api").header("Origin", "https://app.test").build();
+        let mut resp = Response::new(200).with_header("Vary", "Accept-Language, origin");
+        assert!(cors().middleware_before(&req).is_none());
+        cors().middleware_after(&req, &mut resp);
+        assert_eq!(resp.header("Vary"), Some("Accept-Language, origin"));
+        assert_eq!(resp.header("Access-Control-Allow-Origin"), Some("https://app.test"));
+    }
+}
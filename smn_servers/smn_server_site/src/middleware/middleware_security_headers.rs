@@ -0,0 +1,48 @@
+use crate::structs::core::{Request, Response};
+use crate::structs::middleware::Middleware;
+
+pub use smn_shared::security_headers::{SecurityConfig, SecurityOverride, SecurityPolicy};
+
+/// Adds the security-header policy to every response; headers a plugin set itself are kept.
+pub struct MiddlewareSecurityHeaders {
+    config: SecurityConfig,
+}
+
+impl MiddlewareSecurityHeaders {
+    pub fn new(config: SecurityConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Middleware for MiddlewareSecurityHeaders {
+    fn middleware_name(&self) -> &str {
+        "SecurityHeaders"
+    }
+
+    fn middleware_after(&self, req: &Request, resp: &mut Response) {
+        let policy = self.config.policy_for(&req.path);
+
+        for (name, value) in policy.headers(req.scheme == "https") {
+            if resp.header(name).is_none() {
+                resp.set_header(name, &value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RequestBuilder;
+
+    #[test]
+    fn plugin_headers_are_kept() {
+        let mut resp = Response::new(200).with_header("Content-Security-Policy", "default-src 'none'");
+
+        MiddlewareSecurityHeaders::new(SecurityConfig::default())
+            .middleware_after(&RequestBuilder::get("/").build(), &mut resp);
+
+        assert_eq!(resp.header("Content-Security-Policy"), Some("default-src 'none'"));
+        assert_eq!(resp.header("X-Frame-Options"), Some("DENY"));
+    }
+}
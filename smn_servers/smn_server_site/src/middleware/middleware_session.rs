@@ -0,0 +1,423 @@
+use crate::structs::cookie::{Cookie, SameSite};
+use crate::structs::core::{Request, Response};
+use crate::structs::form::{parse_urlencoded, percent_encode};
+use crate::structs::middleware::Middleware;
+use crate::structs::session::{Session, SessionChange};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// SHA-256 block size, for HMAC.
+const BLOCK_SIZE: usize = 64;
+
+/// Session files get a directory of their own, so pruning never touches anything else.
+const SESSION_SUBDIR: &str = "sessions";
+
+/// How often saving a session also sweeps expired session files from disk.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Where session values live between requests.
+pub enum SessionStorage {
+    /// Lost on restart.
+    Memory,
+    /// One file per session in a `sessions` directory under `dir`, surviving restarts.
+    Disk { dir: String },
+}
+
+pub struct SessionConfig {
+    pub cookie_name: String,
+    /// Signs session cookies; changing it logs everyone out.
+    pub secret: Vec<u8>,
+    /// Sessions expire this long after they were last changed.
+    pub max_age: Duration,
+    pub same_site: SameSite,
+    pub storage: SessionStorage,
+}
+
+struct Stored {
+    expires: SystemTime,
+    values: HashMap<String, String>,
+}
+
+/// Attaches a `Session` to every request, keyed by an HMAC-signed cookie, and saves it
+/// after the response. No cookie is set until a plugin stores something.
+pub struct MiddlewareSession {
+    config: SessionConfig,
+    memory: Mutex<HashMap<String, Stored>>,
+    issued: AtomicU64,
+    pruned: Mutex<Instant>,
+}
+
+impl MiddlewareSession {
+    pub fn new(config: SessionConfig) -> Self {
+        if let SessionStorage::Disk { dir } = &config.storage {
+            let dir = Path::new(dir).join(SESSION_SUBDIR);
+            if let Err(e) = fs::create_dir_all(&dir) {
+                println!("Session: cannot create {}: {}", dir.display(), e);
+            }
+            prune_dir(&dir);
+        }
+
+        Self {
+            config,
+            memory: Mutex::new(HashMap::new()),
+            issued: AtomicU64::new(0),
+            pruned: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Unguessable without the secret, and unique within this process.
+    fn new_id(&self) -> String {
+        let issued = self.issued.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(&self.config.secret);
+        hasher.update(issued.to_le_bytes());
+        hasher.update(now.as_nanos().to_le_bytes());
+        hasher.update(RandomState::new().hash_one(issued).to_le_bytes());
+        URL_SAFE_NO_PAD.encode(hasher.finalize())
+    }
+
+    fn sign(&self, id: &str) -> String {
+        URL_SAFE_NO_PAD.encode(hmac_sha256(&self.config.secret, id.as_bytes()))
+    }
+
+    /// The session id from a cookie value of the form `id.signature`.
+    fn verify(&self, value: &str) -> Option<String> {
+        let (id, signature) = value.split_once('.')?;
+        let expected = self.sign(id);
+
+        constant_time_eq(expected.as_bytes(), signature.as_bytes()).then(|| id.to_string())
+    }
+
+    /// An expired session is removed as soon as it is seen.
+    fn load(&self, id: &str) -> Option<HashMap<String, String>> {
+        let stored = match &self.config.storage {
+            SessionStorage::Memory => {
+                let memory = self.memory.lock().unwrap();
+                let stored = memory.get(id)?;
+                Stored {
+                    expires: stored.expires,
+                    values: stored.values.clone(),
+                }
+            }
+            SessionStorage::Disk { dir } => read_file(&session_path(dir, id)?)?,
+        };
+
+        if stored.expires <= SystemTime::now() {
+            self.remove(id);
+            return None;
+        }
+        Some(stored.values)
+    }
+
+    fn save(&self, id: &str, values: HashMap<String, String>) {
+        let now = SystemTime::now();
+        let stored = Stored {
+            expires: now + self.config.max_age,
+            values,
+        };
+
+        match &self.config.storage {
+            SessionStorage::Memory => {
+                let mut memory = self.memory.lock().unwrap();
+                memory.retain(|_, stored| stored.expires > now);
+                memory.insert(id.to_string(), stored);
+            }
+            SessionStorage::Disk { dir } => {
+                if let Some(path) = session_path(dir, id)
+                    && let Err(e) = write_file(&path, &stored)
+                {
+                    println!("Session: cannot write {}: {}", path.display(), e);
+                }
+
+                let mut pruned = self.pruned.lock().unwrap();
+                if pruned.elapsed() >= PRUNE_INTERVAL {
+                    *pruned = Instant::now();
+                    prune_dir(&Path::new(dir).join(SESSION_SUBDIR));
+                }
+            }
+        }
+    }
+
+    fn remove(&self, id: &str) {
+        match &self.config.storage {
+            SessionStorage::Memory => {
+                self.memory.lock().unwrap().remove(id);
+            }
+            SessionStorage::Disk { dir } => {
+                if let Some(path) = session_path(dir, id) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+    }
+
+    fn cookie(&self, req: &Request, value: &str) -> Cookie {
+        Cookie::new(&self.config.cookie_name, value)
+            .path("/")
+            .http_only(true)
+            .secure(req.scheme == "https")
+            .same_site(self.config.same_site)
+    }
+}
+
+impl Middleware for MiddlewareSession {
+    fn middleware_name(&self) -> &str {
+        "Session"
+    }
+
+    fn middleware_prepare(&self, req: &mut Request) {
+        let restored = req
+            .cookie(&self.config.cookie_name)
+            .and_then(|value| self.verify(value))
+            .and_then(|id| self.load(&id).map(|values| Session::restore(id, values)));
+
+        req.session = Some(restored.unwrap_or_default());
+    }
+
+    fn middleware_after(&self, req: &Request, resp: &mut Response) {
+        let Some(session) = &req.session else {
+            return;
+        };
+
+        match session.take_change() {
+            SessionChange::Unchanged => {}
+            SessionChange::Save { id, values, retired } => {
+                if let Some(retired) = retired {
+                    self.remove(&retired);
+                }
+
+                let id = id.unwrap_or_else(|| self.new_id());
+                self.save(&id, values);
+
+                let value = format!("{}.{}", id, self.sign(&id));
+                resp.add_cookie(&self.cookie(req, &value).max_age(self.config.max_age));
+            }
+            SessionChange::Destroy { id } => {
+                if let Some(id) = id {
+                    self.remove(&id);
+                }
+                resp.add_cookie(&self.cookie(req, "").max_age(Duration::ZERO));
+            }
+        }
+    }
+}
+
+/// Ids are base64url, so anything else is refused rather than joined onto a path.
+fn session_path(dir: &str, id: &str) -> Option<PathBuf> {
+    is_session_id(id).then(|| Path::new(dir).join(SESSION_SUBDIR).join(id))
+}
+
+fn is_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+/// Expiry as Unix seconds on the first line, then the values form-encoded.
+fn write_file(path: &Path, stored: &Stored) -> std::io::Result<()> {
+    let expires = stored.expires.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let values: Vec<String> = stored
+        .values
+        .iter()
+        .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+        .collect();
+
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, format!("{}\n{}\n", expires, values.join("&")))?;
+    fs::rename(temp, path)
+}
+
+fn read_file(path: &Path) -> Option<Stored> {
+    let contents = fs::read_to_string(path).ok()?;
+    let (expires, values) = contents.split_once('\n')?;
+
+    Some(Stored {
+        expires: UNIX_EPOCH + Duration::from_secs(expires.trim().parse().ok()?),
+        values: parse_urlencoded(values.trim()).into_iter().collect(),
+    })
+}
+
+/// Deletes session files that have expired. Files that do not look like sessions, by
+/// name or by their expiry line, are left alone.
+fn prune_dir(dir: &Path) {
+    let now = SystemTime::now();
+
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let named_like_session = entry.file_name().to_str().is_some_and(is_session_id);
+
+        if named_like_session && read_file(&path).is_some_and(|stored| stored.expires <= now) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| block.map(|k| k ^ byte);
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::plugin_helloworld::PluginHelloWorld;
+    use crate::server::Server;
+    use crate::structs::config::ServerConfig;
+    use crate::test_support::{RequestBuilder, ScratchDir, send};
+
+    fn middleware() -> MiddlewareSession {
+        middleware_with(SessionStorage::Memory)
+    }
+
+    fn middleware_with(storage: SessionStorage) -> MiddlewareSession {
+        MiddlewareSession::new(config(storage))
+    }
+
+    fn config(storage: SessionStorage) -> SessionConfig {
+        SessionConfig {
+            cookie_name: "sid".to_string(),
+            secret: b"test secret".to_vec(),
+            max_age: Duration::from_secs(600),
+            same_site: SameSite::Lax,
+            storage,
+        }
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let mac = hmac_sha256(&[0x0b; 20], b"Hi There");
+        let hex: String = mac.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        assert_eq!(hex, "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+    }
+
+    #[test]
+    fn session_persists_through_a_signed_cookie() {
+        let server = Server::new(ServerConfig::default(), vec![Box::new(middleware())], vec![PluginHelloWorld.into()]);
+
+        let first = send(&server, RequestBuilder::get("/hello"));
+        first.assert_status(200).assert_body_contains("Visits this session: 1");
+        let set_cookie = first.header("Set-Cookie").unwrap().to_string();
+        assert!(set_cookie.contains("HttpOnly") && set_cookie.contains("SameSite=Lax"));
+        assert!(!set_cookie.contains("Secure"));
+
+        let cookie = set_cookie.split(';').next().unwrap();
+        send(&server, RequestBuilder::get("/hello").header("Cookie", cookie))
+            .assert_body_contains("Visits this session: 2");
+        send(&server, RequestBuilder::get("/hello?reset&from=test").header("Cookie", cookie))
+            .assert_body_contains("Session cleared");
+        send(&server, RequestBuilder::get("/hello").header("Cookie", cookie))
+            .assert_body_contains("Visits this session: 1");
+
+        let (id, _) = cookie.split_once('.').unwrap();
+        send(&server, RequestBuilder::get("/hello").header("Cookie", &format!("{}.forged", id)))
+            .assert_body_contains("Visits this session: 1");
+    }
+
+    #[test]
+    fn disk_sessions_prune_only_their_own_expired_files() {
+        let dir = ScratchDir::new("sessions");
+        let sessions = dir.join(SESSION_SUBDIR);
+        fs::create_dir_all(&sessions).unwrap();
+
+        fs::write(dir.join("notes.txt"), "kept").unwrap();
+        fs::write(sessions.join("expired"), "1\n").unwrap();
+        fs::write(sessions.join("not-a-session"), "hello\n").unwrap();
+        fs::write(sessions.join("backup.tmp"), "1\n").unwrap();
+
+        let server = Server::new(
+            ServerConfig::default(),
+            vec![Box::new(middleware_with(SessionStorage::Disk {
+                dir: dir.path().to_string_lossy().into_owned(),
+            }))],
+            vec![PluginHelloWorld.into()],
+        );
+
+        assert!(!sessions.join("expired").exists());
+        assert!(sessions.join("not-a-session").exists());
+        assert!(sessions.join("backup.tmp").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        let set_cookie = send(&server, RequestBuilder::get("/hello")).header("Set-Cookie").unwrap().to_string();
+        let (id, _) = set_cookie.split_once('.').unwrap();
+        let id = id.trim_start_matches("sid=");
+        assert!(sessions.join(id).exists());
+    }
+
+    /// Runs `change` against a session restored from `id`, then saves it the way a
+    /// response would, returning the id in the new cookie.
+    fn after_change(middleware: &MiddlewareSession, id: &str, change: impl FnOnce(&Session)) -> Option<String> {
+        let session = Session::restore(id.to_string(), middleware.load(id).unwrap());
+        change(&session);
+
+        let mut req = RequestBuilder::get("/hello").build();
+        req.session = Some(session);
+        let mut resp = Response::new(200);
+        middleware.middleware_after(&req, &mut resp);
+
+        let cookie = resp.header("Set-Cookie")?.split(';').next()?;
+        let (id, _) = cookie.trim_start_matches("sid=").split_once('.')?;
+        Some(id.to_string())
+    }
+
+    #[test]
+    fn destroy_and_regenerate_retire_the_old_id() {
+        let middleware = middleware();
+        let values = HashMap::from([("user".to_string(), "alice".to_string())]);
+
+        middleware.save("planted", values.clone());
+        let new_id = after_change(&middleware, "planted", |session| session.regenerate()).unwrap();
+        assert_ne!(new_id, "planted");
+        assert!(middleware.load("planted").is_none());
+        assert_eq!(middleware.load(&new_id), Some(values.clone()));
+
+        middleware.save("old", values);
+        let fresh = after_change(&middleware, "old", |session| {
+            session.destroy();
+            session.set("flash", "Logged out");
+        })
+        .unwrap();
+        assert_ne!(fresh, "old");
+        assert!(middleware.load("old").is_none());
+        assert_eq!(middleware.load(&fresh).unwrap().get("user"), None);
+    }
+
+    #[test]
+    fn expired_disk_session_is_removed_when_loaded() {
+        let dir = ScratchDir::new("expired-sessions");
+        let middleware = MiddlewareSession::new(SessionConfig {
+            max_age: Duration::ZERO,
+            ..config(SessionStorage::Disk {
+                dir: dir.path().to_string_lossy().into_owned(),
+            })
+        });
+
+        middleware.save("stale", HashMap::new());
+        assert!(dir.join(SESSION_SUBDIR).join("stale").exists());
+        assert!(middleware.load("stale").is_none());
+        assert!(!dir.join(SESSION_SUBDIR).join("stale").exists());
+    }
+}
@@ -0,0 +1,5 @@
+
+pub mod middleware_auth;
+pub mod middleware_cors;
+pub mod middleware_security_headers;
+pub mod middleware_session;
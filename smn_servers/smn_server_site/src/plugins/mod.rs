@@ -1,3 +1,10 @@
 
 pub mod plugin_static_files;
-pub mod plugin_helloworld;
\ No newline at end of file
+pub mod plugin_helloworld;
+pub mod plugin_echo_socket;
+pub mod plugin_contact_form;
+pub mod plugin_upload;
+pub mod plugin_sitemap;
+pub mod plugin_blog;
+#[cfg(feature = "async")]
+pub mod plugin_get_status;
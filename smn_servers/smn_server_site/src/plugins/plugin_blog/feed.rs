@@ -0,0 +1,85 @@
+use super::BlogConfig;
+use super::post::Post;
+use crate::structs::http_date::{http_date, rfc3339};
+use crate::structs::xml::xml_escape;
+use std::time::UNIX_EPOCH;
+
+/// RSS 2.0 channel of the given posts, newest first.
+pub fn rss(config: &BlogConfig, base_url: &str, posts: &[&Post]) -> String {
+    let home = format!("{}{}/", base_url, config.mount);
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n",
+    );
+    xml.push_str(&format!("  <title>{}</title>\n", xml_escape(&config.title)));
+    xml.push_str(&format!("  <link>{}</link>\n", xml_escape(&home)));
+    xml.push_str(&format!("  <description>{}</description>\n", xml_escape(&config.description)));
+    xml.push_str(&format!(
+        "  <atom:link href=\"{}rss.xml\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        xml_escape(&home)
+    ));
+
+    if let Some(latest) = posts.first() {
+        xml.push_str(&format!("  <lastBuildDate>{}</lastBuildDate>\n", http_date(latest.date)));
+    }
+
+    for post in posts {
+        let link = format!("{}{}", base_url, post.permalink(&config.mount));
+        xml.push_str(&format!(
+            "  <item>\n    <title>{}</title>\n    <link>{}</link>\n    <guid isPermaLink=\"true\">{}</guid>\n    <pubDate>{}</pubDate>\n",
+            xml_escape(&post.title),
+            xml_escape(&link),
+            xml_escape(&link),
+            http_date(post.date)
+        ));
+        for tag in &post.tags {
+            xml.push_str(&format!("    <category>{}</category>\n", xml_escape(tag)));
+        }
+        xml.push_str(&format!(
+            "    <description>{}</description>\n  </item>\n",
+            xml_escape(&post.html)
+        ));
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Atom 1.0 feed of the given posts, newest first.
+pub fn atom(config: &BlogConfig, base_url: &str, posts: &[&Post]) -> String {
+    let home = format!("{}{}/", base_url, config.mount);
+    let updated = rfc3339(posts.first().map_or(UNIX_EPOCH, |post| post.date));
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+    );
+    xml.push_str(&format!("  <title>{}</title>\n", xml_escape(&config.title)));
+    xml.push_str(&format!("  <subtitle>{}</subtitle>\n", xml_escape(&config.description)));
+    xml.push_str(&format!("  <id>{}</id>\n", xml_escape(&home)));
+    xml.push_str(&format!("  <link href=\"{}\"/>\n", xml_escape(&home)));
+    xml.push_str(&format!("  <link href=\"{}atom.xml\" rel=\"self\"/>\n", xml_escape(&home)));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated));
+
+    for post in posts {
+        let link = format!("{}{}", base_url, post.permalink(&config.mount));
+        xml.push_str(&format!(
+            "  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    <link href=\"{}\"/>\n    <updated>{}</updated>\n",
+            xml_escape(&post.title),
+            xml_escape(&link),
+            xml_escape(&link),
+            rfc3339(post.date)
+        ));
+        for tag in &post.tags {
+            xml.push_str(&format!("    <category term=\"{}\"/>\n", xml_escape(tag)));
+        }
+        xml.push_str(&format!(
+            "    <author><name>{}</name></author>\n    <content type=\"html\">{}</content>\n  </entry>\n",
+            xml_escape(&config.author),
+            xml_escape(&post.html)
+        ));
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
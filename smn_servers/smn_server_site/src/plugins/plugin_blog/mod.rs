@@ -0,0 +1,298 @@
+mod feed;
+mod post;
+mod render;
+
+use crate::structs::context::PluginContext;
+use crate::structs::core::{Request, Response};
+use crate::structs::plugin::Plugin;
+use post::{Post, load_posts, slugify};
+use render::Listing;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long the parsed posts are trusted before `posts_dir` is scanned again. Dev mode
+/// scans on every request so an edit shows up on the next refresh.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct BlogConfig {
+    /// Markdown posts with front-matter, one file per post.
+    pub posts_dir: String,
+    /// URL prefix without a trailing slash, e.g. `/blog`.
+    pub mount: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub posts_per_page: usize,
+    /// Number of recent posts in the RSS and Atom feeds.
+    pub feed_size: usize,
+}
+
+struct Loaded {
+    fingerprint: u64,
+    checked: Instant,
+    posts: Arc<Vec<Post>>,
+}
+
+/// A devlog served from Markdown files: paginated index and tag pages, post
+/// permalinks and RSS/Atom feeds. Drafts are only shown in dev mode.
+pub struct PluginBlog {
+    config: BlogConfig,
+    loaded: Mutex<Option<Loaded>>,
+}
+
+impl PluginBlog {
+    pub fn new(config: BlogConfig) -> Self {
+        Self {
+            config,
+            loaded: Mutex::new(None),
+        }
+    }
+
+    /// The parsed posts, re-read when a file in `posts_dir` has changed since the last
+    /// scan, which runs at most once per `RECHECK_INTERVAL`.
+    fn posts(&self, ctx: &PluginContext) -> Arc<Vec<Post>> {
+        let dir = Path::new(&self.config.posts_dir);
+        let interval = if ctx.config().dev_mode {
+            Duration::ZERO
+        } else {
+            RECHECK_INTERVAL
+        };
+        let mut loaded = self.loaded.lock().unwrap();
+
+        if let Some(current) = loaded.as_mut() {
+            if current.checked.elapsed() < interval {
+                return Arc::clone(&current.posts);
+            }
+            current.checked = Instant::now();
+        }
+
+        let fingerprint = fingerprint(dir);
+        if let Some(current) = loaded.as_ref()
+            && current.fingerprint == fingerprint
+        {
+            return Arc::clone(&current.posts);
+        }
+
+        let posts = Arc::new(load_posts(dir, |message| ctx.log(message)));
+        ctx.log(&format!("loaded {} post(s) from {}", posts.len(), self.config.posts_dir));
+
+        *loaded = Some(Loaded {
+            fingerprint,
+            checked: Instant::now(),
+            posts: Arc::clone(&posts),
+        });
+        posts
+    }
+
+    fn listing(&self, posts: &[&Post], tag: Option<&str>, page: usize) -> Response {
+        let per_page = self.config.posts_per_page.max(1);
+        let pages = posts.len().div_ceil(per_page).max(1);
+
+        if page == 0 || page > pages {
+            return not_found();
+        }
+
+        let shown: Vec<&Post> = posts.iter().skip((page - 1) * per_page).take(per_page).copied().collect();
+        Response::html(render::listing_page(&self.config, &shown, &Listing { tag, page, pages }))
+    }
+}
+
+impl Plugin for PluginBlog {
+    fn plugin_name(&self) -> &str {
+        "Blog"
+    }
+
+    fn plugin_init(&self, ctx: &PluginContext) {
+        self.posts(ctx);
+    }
+
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        let path = req.path.split('?').next().unwrap_or("");
+        req.method == "GET"
+            && (path == self.config.mount || path.starts_with(&format!("{}/", self.config.mount)))
+    }
+
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response {
+        let all = self.posts(ctx);
+        let dev_mode = ctx.config().dev_mode;
+        let posts: Vec<&Post> = all.iter().filter(|post| dev_mode || !post.draft).collect();
+
+        let path = req.path.split('?').next().unwrap_or("");
+        let rest = path[self.config.mount.len()..].trim_matches('/');
+        let segments: Vec<&str> = rest.split('/').filter(|segment| !segment.is_empty()).collect();
+        let base_url = ctx.config().base_url.trim_end_matches('/');
+        let recent = &posts[..posts.len().min(self.config.feed_size)];
+
+        match segments.as_slice() {
+            [] => self.listing(&posts, None, 1),
+            ["page", page] => self.listing(&posts, None, page.parse().unwrap_or(0)),
+            ["rss.xml"] => Response::new(200)
+                .with_header("Content-Type", "application/rss+xml; charset=utf-8")
+                .with_body(feed::rss(&self.config, base_url, recent)),
+            ["atom.xml"] => Response::new(200)
+                .with_header("Content-Type", "application/atom+xml; charset=utf-8")
+                .with_body(feed::atom(&self.config, base_url, recent)),
+            ["tags", tag, tail @ ..] => {
+                let page = match tail {
+                    [] => 1,
+                    ["page", page] => page.parse().unwrap_or(0),
+                    _ => return not_found(),
+                };
+
+                let tagged: Vec<&Post> = posts
+                    .iter()
+                    .filter(|post| post.tags.iter().any(|t| slugify(t) == *tag))
+                    .copied()
+                    .collect();
+                let Some(name) = tagged
+                    .first()
+                    .and_then(|post| post.tags.iter().find(|t| slugify(t) == *tag))
+                else {
+                    return not_found();
+                };
+
+                self.listing(&tagged, Some(name.as_str()), page)
+            }
+            [_, _] => posts
+                .iter()
+                .find(|post| post.permalink(&self.config.mount).trim_matches('/') == path.trim_matches('/'))
+                .map(|post| Response::html(render::post_page(&self.config, post)))
+                .unwrap_or_else(not_found),
+            _ => not_found(),
+        }
+    }
+}
+
+fn not_found() -> Response {
+    Response::new(404)
+        .with_header("Content-Type", "text/html; charset=utf-8")
+        .with_body("<h1>Post not found</h1>")
+}
+
+/// Changes whenever a post is added, removed or modified.
+fn fingerprint(dir: &Path) -> u64 {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            Some((entry.file_name(), meta.len(), meta.modified().ok()))
+        })
+        .collect();
+    entries.sort();
+
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::structs::config::ServerConfig;
+    use crate::test_support::{RequestBuilder, ScratchDir, dispatch};
+
+    fn posts_dir(name: &str) -> ScratchDir {
+        let dir = ScratchDir::new(&format!("blog-{}", name));
+
+        for (file, front) in [
+            ("first.md", "title: First\ndate: 2024-01-05\ntags: [Rust, Game Dev]"),
+            ("second.md", "title: Second\ndate: 2024-02-05 09:30\ntags: rust"),
+            ("third.md", "title: Third & last\ndate: 2025-03-01"),
+            ("wip.md", "title: Work in progress\ndate: 2025-04-01\ndraft: true"),
+        ] {
+            fs::write(dir.join(file), format!("---\n{}\n---\nBody of *{}*\n", front, file)).unwrap();
+        }
+
+        dir
+    }
+
+    fn server(dir: &Path, dev_mode: bool) -> Server {
+        let plugin = PluginBlog::new(BlogConfig {
+            posts_dir: dir.to_string_lossy().to_string(),
+            mount: "/blog".to_string(),
+            title: "Devlog".to_string(),
+            description: "Notes".to_string(),
+            author: "Tester".to_string(),
+            posts_per_page: 2,
+            feed_size: 10,
+        });
+        let config = ServerConfig {
+            dev_mode,
+            ..ServerConfig::default()
+        };
+
+        Server::new(config, Vec::new(), vec![plugin.into()])
+    }
+
+    #[test]
+    fn index_is_paginated_newest_first() {
+        let dir = posts_dir("pages");
+        let server = server(dir.path(), false);
+
+        dispatch(&server, RequestBuilder::get("/blog/"))
+            .assert_status(200)
+            .assert_body_contains("Third &amp; last")
+            .assert_body_contains("/blog/page/2/");
+        dispatch(&server, RequestBuilder::get("/blog/page/2/"))
+            .assert_status(200)
+            .assert_body_contains("/blog/2024/first/");
+        dispatch(&server, RequestBuilder::get("/blog/page/3/")).assert_status(404);
+    }
+
+    #[test]
+    fn drafts_only_show_in_dev_mode() {
+        let dir = posts_dir("drafts");
+
+        dispatch(&server(dir.path(), false), RequestBuilder::get("/blog/2025/wip/")).assert_status(404);
+        dispatch(&server(dir.path(), true), RequestBuilder::get("/blog/2025/wip/"))
+            .assert_status(200)
+            .assert_body_contains("<strong>Draft</strong>");
+    }
+
+    #[test]
+    fn tag_pages_and_feeds() {
+        let dir = posts_dir("feeds");
+        let server = server(dir.path(), false);
+
+        dispatch(&server, RequestBuilder::get("/blog/tags/game-dev/"))
+            .assert_status(200)
+            .assert_body_contains("First");
+        dispatch(&server, RequestBuilder::get("/blog/tags/missing/")).assert_status(404);
+
+        let rss = dispatch(&server, RequestBuilder::get("/blog/rss.xml"));
+        rss.assert_status(200)
+            .assert_body_contains("<pubDate>Mon, 05 Feb 2024 09:30:00 GMT</pubDate>");
+        assert!(!rss.text().contains("Work in progress"));
+
+        dispatch(&server, RequestBuilder::get("/blog/atom.xml"))
+            .assert_status(200)
+            .assert_body_contains("<updated>2025-03-01T00:00:00Z</updated>");
+    }
+
+    #[test]
+    fn new_posts_wait_for_the_recheck_outside_dev_mode() {
+        let dir = posts_dir("recheck");
+        let live = server(dir.path(), false);
+        let dev = server(dir.path(), true);
+        dispatch(&live, RequestBuilder::get("/blog/")).assert_status(200);
+        dispatch(&dev, RequestBuilder::get("/blog/")).assert_status(200);
+
+        fs::write(dir.join("fourth.md"), "---\ntitle: Fourth\ndate: 2025-05-01\n---\nNew\n").unwrap();
+
+        dispatch(&live, RequestBuilder::get("/blog/2025/fourth/")).assert_status(404);
+        dispatch(&dev, RequestBuilder::get("/blog/2025/fourth/")).assert_status(200);
+    }
+
+    #[test]
+    fn slugs_are_url_safe() {
+        assert_eq!(slugify("Game Dev"), "game-dev");
+        assert_eq!(slugify("  Hello, World!  "), "hello-world");
+    }
+}
@@ -0,0 +1,134 @@
+use crate::structs::http_date::{DateTime, from_civil};
+use pulldown_cmark::{Options, Parser, html};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+pub struct Post {
+    /// From the file name, e.g. `first-light.md` becomes `first-light`.
+    pub slug: String,
+    pub title: String,
+    pub date: SystemTime,
+    pub tags: Vec<String>,
+    pub draft: bool,
+    /// The Markdown body rendered to HTML.
+    pub html: String,
+}
+
+impl Post {
+    /// `/blog/2024/first-light/` style path under `mount`.
+    pub fn permalink(&self, mount: &str) -> String {
+        format!("{}/{}/{}/", mount, DateTime::from_system_time(self.date).year, self.slug)
+    }
+
+    /// Parses a post with a `---` delimited front-matter block of `key: value` lines.
+    fn parse(slug: String, source: &str) -> Result<Self, String> {
+        let source = source.trim_start_matches('\u{feff}');
+        let rest = source
+            .strip_prefix("---")
+            .ok_or("missing front-matter")?;
+        let (front, body) = rest
+            .split_once("\n---")
+            .ok_or("unterminated front-matter")?;
+        let body = body.split_once('\n').map_or("", |(_, body)| body);
+
+        let mut title = None;
+        let mut date = None;
+        let mut tags = Vec::new();
+        let mut draft = false;
+
+        for line in front.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim() {
+                "title" => title = Some(value.trim_matches('"').to_string()),
+                "date" => date = Some(parse_date(value).ok_or(format!("bad date '{}'", value))?),
+                "tags" => {
+                    tags = value
+                        .trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .split(',')
+                        .map(|tag| tag.trim().trim_matches('"').to_lowercase())
+                        .filter(|tag| !tag.is_empty())
+                        .collect()
+                }
+                "draft" => draft = value == "true",
+                _ => {}
+            }
+        }
+
+        let mut html = String::new();
+        let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES;
+        html::push_html(&mut html, Parser::new_ext(body, options));
+
+        Ok(Self {
+            slug,
+            title: title.ok_or("missing title")?,
+            date: date.ok_or("missing date")?,
+            tags,
+            draft,
+            html,
+        })
+    }
+}
+
+/// Reads every `.md` file in `dir`, newest first. Posts that fail to parse are
+/// reported through `log` and skipped.
+pub fn load_posts(dir: &Path, log: impl Fn(&str)) -> Vec<Post> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        log(&format!("cannot read {}", dir.display()));
+        return Vec::new();
+    };
+
+    let mut posts: Vec<Post> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| {
+            let slug = slugify(&path.file_stem()?.to_string_lossy());
+            let source = fs::read_to_string(&path).ok()?;
+
+            Post::parse(slug, &source)
+                .map_err(|e| log(&format!("skipping {}: {}", path.display(), e)))
+                .ok()
+        })
+        .collect();
+
+    posts.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.slug.cmp(&b.slug)));
+    posts
+}
+
+/// Lowercase ASCII letters, digits and single dashes.
+pub fn slugify(raw: &str) -> String {
+    let mut slug = String::new();
+
+    for c in raw.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') && !slug.is_empty() {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+/// `YYYY-MM-DD`, optionally followed by `HH:MM`, in UTC.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim_matches('"');
+    let (date, time) = value.split_once([' ', 'T']).unwrap_or((value, "00:00"));
+
+    let mut date_parts = date.splitn(3, '-');
+    let year = date_parts.next()?.parse().ok()?;
+    let month = date_parts.next()?.parse().ok()?;
+    let day = date_parts.next()?.parse().ok()?;
+
+    let mut time_parts = time.splitn(3, ':');
+    let hour = time_parts.next()?.parse().ok()?;
+    let minute = time_parts.next()?.trim_end_matches('Z').parse().ok()?;
+
+    from_civil(year, month, day, hour, minute)
+}
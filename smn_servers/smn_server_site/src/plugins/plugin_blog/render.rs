@@ -0,0 +1,119 @@
+use super::BlogConfig;
+use super::post::{Post, slugify};
+use crate::structs::http_date::DateTime;
+use crate::structs::xml::xml_escape;
+
+/// Where a listing sits, for its heading and pagination links.
+pub struct Listing<'a> {
+    /// `None` for the main index.
+    pub tag: Option<&'a str>,
+    pub page: usize,
+    pub pages: usize,
+}
+
+pub fn listing_page(config: &BlogConfig, posts: &[&Post], listing: &Listing) -> String {
+    let heading = match listing.tag {
+        Some(tag) => format!("Posts tagged “{}”", xml_escape(tag)),
+        None => xml_escape(&config.title),
+    };
+
+    let mut body = format!("<h1>{}</h1>\n", heading);
+
+    if posts.is_empty() {
+        body.push_str("<p>No posts yet.</p>\n");
+    }
+
+    for post in posts {
+        body.push_str(&format!(
+            "<article>\n<h2><a href=\"{}\">{}</a></h2>\n{}</article>\n",
+            xml_escape(&post.permalink(&config.mount)),
+            xml_escape(&post.title),
+            meta(config, post)
+        ));
+    }
+
+    body.push_str(&pagination(config, listing));
+    layout(config, &heading, &body)
+}
+
+pub fn post_page(config: &BlogConfig, post: &Post) -> String {
+    let draft = if post.draft { "<p><strong>Draft</strong></p>\n" } else { "" };
+    let body = format!(
+        "<article>\n<h1>{}</h1>\n{}{}{}</article>\n",
+        xml_escape(&post.title),
+        meta(config, post),
+        draft,
+        post.html
+    );
+
+    layout(config, &xml_escape(&post.title), &body)
+}
+
+/// Base path of a tag's listing, e.g. `/blog/tags/rust/`.
+pub fn tag_path(config: &BlogConfig, tag: &str) -> String {
+    format!("{}/tags/{}/", config.mount, slugify(tag))
+}
+
+fn meta(config: &BlogConfig, post: &Post) -> String {
+    let date = DateTime::from_system_time(post.date);
+    let tags: Vec<String> = post
+        .tags
+        .iter()
+        .map(|tag| format!("<a href=\"{}\">#{}</a>", xml_escape(&tag_path(config, tag)), xml_escape(tag)))
+        .collect();
+
+    format!(
+        "<p><time datetime=\"{:04}-{:02}-{:02}\">{:04}-{:02}-{:02}</time> {}</p>\n",
+        date.year,
+        date.month,
+        date.day,
+        date.year,
+        date.month,
+        date.day,
+        tags.join(" ")
+    )
+}
+
+fn pagination(config: &BlogConfig, listing: &Listing) -> String {
+    if listing.pages <= 1 {
+        return String::new();
+    }
+
+    let base = match listing.tag {
+        Some(tag) => tag_path(config, tag),
+        None => format!("{}/", config.mount),
+    };
+    let link = |page: usize| match page {
+        1 => base.clone(),
+        n => format!("{}page/{}/", base, n),
+    };
+
+    let mut nav = String::from("<nav>");
+    if listing.page > 1 {
+        nav.push_str(&format!("<a rel=\"prev\" href=\"{}\">Newer</a> ", link(listing.page - 1)));
+    }
+    nav.push_str(&format!("Page {} of {}", listing.page, listing.pages));
+    if listing.page < listing.pages {
+        nav.push_str(&format!(" <a rel=\"next\" href=\"{}\">Older</a>", link(listing.page + 1)));
+    }
+    nav.push_str("</nav>\n");
+    nav
+}
+
+fn layout(config: &BlogConfig, title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n<title>{}</title>\n\
+<link rel=\"alternate\" type=\"application/rss+xml\" title=\"{}\" href=\"{}/rss.xml\">\n\
+<link rel=\"alternate\" type=\"application/atom+xml\" title=\"{}\" href=\"{}/atom.xml\">\n\
+</head>\n<body>\n<header><a href=\"{}/\">{}</a></header>\n<main>\n{}</main>\n</body>\n</html>\n",
+        title,
+        xml_escape(&config.title),
+        config.mount,
+        xml_escape(&config.title),
+        config.mount,
+        config.mount,
+        xml_escape(&config.title),
+        body
+    )
+}
@@ -0,0 +1,255 @@
+use crate::structs::context::PluginContext;
+use crate::structs::core::{Request, Response};
+use crate::structs::form::{field, parse_urlencoded};
+use crate::structs::http_date::rfc3339;
+use crate::structs::json::json_escape;
+use crate::structs::plugin::Plugin;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+const MAX_NAME_LEN: usize = 100;
+const MAX_EMAIL_LEN: usize = 254;
+const MAX_MESSAGE_LEN: usize = 5000;
+
+pub struct ContactFormConfig {
+    /// Where the form posts to.
+    pub path: String,
+    /// Submissions are appended here, one JSON object per line.
+    pub store_path: String,
+    /// Where a successful submission is redirected.
+    pub thank_you_path: String,
+    /// Hidden field that people leave empty; bots that fill it are dropped silently.
+    pub honeypot_field: String,
+}
+
+/// Accepts contact form POSTs and appends them to a JSON-lines file.
+///
+/// Submissions are throttled by a `RateLimitRule` on `path` in `ServerConfig::rate_limit`.
+pub struct PluginContactForm {
+    config: ContactFormConfig,
+    store: Mutex<()>,
+}
+
+struct Submission {
+    name: String,
+    email: String,
+    message: String,
+}
+
+impl PluginContactForm {
+    pub fn new(config: ContactFormConfig) -> Self {
+        Self {
+            config,
+            store: Mutex::new(()),
+        }
+    }
+
+    fn validate(pairs: &[(String, String)]) -> Result<Submission, Vec<&'static str>> {
+        let name = field(pairs, "name").unwrap_or("").trim();
+        let email = field(pairs, "email").unwrap_or("").trim();
+        let message = field(pairs, "message").unwrap_or("").trim();
+
+        let mut errors = Vec::new();
+
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            errors.push("Please enter your name (up to 100 characters).");
+        }
+
+        let valid_email = email.len() <= MAX_EMAIL_LEN
+            && !email.contains(char::is_whitespace)
+            && email
+                .split_once('@')
+                .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.') && !domain.ends_with('.'));
+        if !valid_email {
+            errors.push("Please enter a valid email address.");
+        }
+
+        if message.is_empty() || message.chars().count() > MAX_MESSAGE_LEN {
+            errors.push("Please enter a message (up to 5000 characters).");
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Submission {
+            name: name.to_string(),
+            email: email.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    fn store(&self, submission: &Submission) -> std::io::Result<()> {
+        let line = format!(
+            "{{\"received\":\"{}\",\"name\":\"{}\",\"email\":\"{}\",\"message\":\"{}\"}}\n",
+            rfc3339(SystemTime::now()),
+            json_escape(&submission.name),
+            json_escape(&submission.email),
+            json_escape(&submission.message)
+        );
+
+        // One write per line under the lock keeps concurrent submissions from interleaving
+        let _guard = self.store.lock().unwrap();
+
+        if let Some(parent) = Path::new(&self.config.store_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.store_path)?
+            .write_all(line.as_bytes())
+    }
+
+    fn redirect(&self) -> Response {
+        Response::new(303).with_header("Location", &self.config.thank_you_path)
+    }
+}
+
+impl Plugin for PluginContactForm {
+    fn plugin_name(&self) -> &str {
+        "ContactForm"
+    }
+
+    fn plugin_init(&self, ctx: &PluginContext) {
+        ctx.log(&format!(
+            "accepting submissions on {}, storing to {}",
+            self.config.path, self.config.store_path
+        ));
+    }
+
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        req.path == self.config.path
+    }
+
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response {
+        match req.method.as_str() {
+            "POST" => {}
+            "OPTIONS" => return Response::new(204).with_header("Allow", "POST, OPTIONS"),
+            _ => return Response::new(405).with_header("Allow", "POST, OPTIONS"),
+        }
+
+        let form_type = req
+            .header("Content-Type")
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/x-www-form-urlencoded"));
+        if !form_type {
+            return Response::new(415);
+        }
+
+        let pairs = parse_urlencoded(&String::from_utf8_lossy(&req.body));
+
+        // Look like a success so the bot has nothing to learn from
+        if field(&pairs, &self.config.honeypot_field).is_some_and(|value| !value.is_empty()) {
+            ctx.log("dropped a submission that filled the honeypot");
+            return self.redirect();
+        }
+
+        let submission = match Self::validate(&pairs) {
+            Ok(submission) => submission,
+            Err(errors) => return error_page(400, &errors),
+        };
+
+        match self.store(&submission) {
+            Ok(()) => self.redirect(),
+            Err(e) => {
+                ctx.log(&format!("failed to store submission: {}", e));
+                error_page(500, &["Your message could not be saved, please try again later."])
+            }
+        }
+    }
+}
+
+fn error_page(status: u16, errors: &[&str]) -> Response {
+    let items: String = errors.iter().map(|error| format!("<li>{}</li>", error)).collect();
+
+    Response::new(status).with_header("Content-Type", "text/html; charset=utf-8").with_body(format!(
+        "<!DOCTYPE html>\
+<html><body><h1>Message not sent</h1><ul>{}</ul><p>Please go back and try again.</p></body></html>",
+        items
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::config::ServerConfig;
+    use crate::test_support::{RequestBuilder, ScratchDir, dispatch};
+    use crate::server::Server;
+
+    const STORE_FILE: &str = "submissions.jsonl";
+
+    /// The server and the directory it stores submissions in.
+    fn server(name: &str) -> (Server, ScratchDir) {
+        let dir = ScratchDir::new(&format!("contact-{}", name));
+
+        let plugin = PluginContactForm::new(ContactFormConfig {
+            path: "/contact/submit".to_string(),
+            store_path: dir.join(STORE_FILE).to_string_lossy().to_string(),
+            thank_you_path: "/thanks.html".to_string(),
+            honeypot_field: "website".to_string(),
+        });
+
+        (Server::new(ServerConfig::default(), Vec::new(), vec![plugin.into()]), dir)
+    }
+
+    #[test]
+    fn valid_submission_is_stored_and_redirected() {
+        let (server, dir) = server("valid");
+        let req = RequestBuilder::post("/contact/submit").form(&[
+            ("name", "Ada"),
+            ("email", "ada@example.com"),
+            ("message", "Hello \"there\"\nsecond line"),
+        ]);
+
+        dispatch(&server, req)
+            .assert_status(303)
+            .assert_header("Location", "/thanks.html");
+
+        let stored = fs::read_to_string(dir.join(STORE_FILE)).unwrap();
+        assert!(stored.contains(r#""message":"Hello \"there\"\nsecond line""#), "{}", stored);
+    }
+
+    #[test]
+    fn invalid_submission_lists_errors() {
+        let (server, dir) = server("invalid");
+        let req = RequestBuilder::post("/contact/submit").form(&[("name", "Ada"), ("email", "nope")]);
+
+        dispatch(&server, req)
+            .assert_status(400)
+            .assert_body_contains("valid email")
+            .assert_body_contains("enter a message");
+        assert!(!dir.join(STORE_FILE).exists());
+    }
+
+    #[test]
+    fn honeypot_submission_is_dropped_quietly() {
+        let (server, dir) = server("honeypot");
+        let req = RequestBuilder::post("/contact/submit").form(&[
+            ("name", "Bot"),
+            ("email", "bot@example.com"),
+            ("message", "spam"),
+            ("website", "http://spam.example"),
+        ]);
+
+        dispatch(&server, req).assert_status(303);
+        assert!(!dir.join(STORE_FILE).exists());
+    }
+
+    #[test]
+    fn non_form_posts_are_refused() {
+        let (server, _) = server("json");
+        let req = RequestBuilder::post("/contact/submit")
+            .header("Content-Type", "application/json")
+            .body("{}");
+
+        dispatch(&server, req).assert_status(415);
+        dispatch(&server, RequestBuilder::get("/contact/submit"))
+            .assert_status(405)
+            .assert_header("Allow", "POST, OPTIONS");
+    }
+}
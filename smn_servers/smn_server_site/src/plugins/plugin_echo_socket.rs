@@ -0,0 +1,42 @@
+use crate::structs::context::PluginContext;
+use crate::structs::core::{Request, Response};
+use crate::structs::plugin::Plugin;
+use crate::structs::websocket::{Message, WebSocketHandler};
+
+/// Echoes every WebSocket message on `/ws/echo` back to the sender.
+pub struct PluginEchoSocket;
+
+impl Plugin for PluginEchoSocket {
+    fn plugin_name(&self) -> &str {
+        "EchoSocket"
+    }
+
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        req.path == "/ws/echo"
+    }
+
+    fn plugin_serve(&self, _req: &Request, _ctx: &PluginContext) -> Response {
+        Response::new(426).with_header("Upgrade", "websocket")
+    }
+
+    fn plugin_websocket(&self, _req: &Request, ctx: &PluginContext) -> Option<WebSocketHandler> {
+        let ctx = ctx.clone();
+
+        Some(Box::new(move |mut socket| {
+            while let Ok(message) = socket.recv() {
+                let sent = match message {
+                    Message::Text(text) => socket.send_text(&text),
+                    Message::Binary(data) => socket.send_binary(&data),
+                    Message::Close(code, reason) => {
+                        ctx.log(&format!("closed by client: {} {}", code, reason));
+                        break;
+                    }
+                };
+
+                if sent.is_err() {
+                    break;
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,56 @@
+use crate::structs::context::PluginContext;
+use crate::structs::core::{Request, Response};
+use crate::structs::plugin::{AsyncPlugin, PluginFuture};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+const GET_SERVER_ADDR: &str = "127.0.0.1:33031";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reports whether smn_server_get is ready, as JSON on `/status/get`.
+pub struct PluginGetStatus;
+
+impl AsyncPlugin for PluginGetStatus {
+    fn plugin_name(&self) -> &str {
+        "GetStatus"
+    }
+
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        req.method == "GET" && req.path == "/status/get"
+    }
+
+    fn plugin_serve<'a>(&'a self, _req: &'a Request, ctx: &'a PluginContext) -> PluginFuture<'a> {
+        Box::pin(async move {
+            let ready = match timeout(PROBE_TIMEOUT, probe_ready()).await {
+                Ok(Ok(ready)) => ready,
+                Ok(Err(e)) => {
+                    ctx.log(&format!("probe failed: {}", e));
+                    false
+                }
+                Err(_) => {
+                    ctx.log("probe timed out");
+                    false
+                }
+            };
+
+            Response::new(200)
+                .with_header("Content-Type", "application/json")
+                .with_header("Cache-Control", "no-store")
+                .with_body(format!("{{\"service\":\"smn_server_get\",\"ready\":{}}}", ready))
+        })
+    }
+}
+
+async fn probe_ready() -> std::io::Result<bool> {
+    let mut stream = TcpStream::connect(GET_SERVER_ADDR).await?;
+    stream
+        .write_all(b"GET /readyz HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n")
+        .await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+
+    Ok(reply.starts_with(b"HTTP/1.1 200"))
+}
@@ -1,33 +1,81 @@
-use crate::{structs::plugin::Plugin, structs::core::{Request, Response}};
+use crate::{structs::plugin::Plugin, structs::context::PluginContext, structs::core::{Request, Response}};
+use crate::structs::form::{field, parse_urlencoded};
+use std::time::Instant;
+
+/// The demo page; `?reset` clears the visit count and `?rotate` moves it to a new session id.
+const PATH: &str = "/hello";
 
 pub struct PluginHelloWorld;
 
+/// Registered under "HelloWorld" in `ServerConfig::plugin_settings`.
+pub struct HelloWorldSettings {
+    pub heading: String,
+}
+
 impl Plugin for PluginHelloWorld {
     fn plugin_name(&self) -> &str {
         "HelloWorld"
     }
 
-    fn plugin_init(&self) {
-        println!("HelloWorld from a plugin.");
+    fn plugin_init(&self, ctx: &PluginContext) {
+        // The server has already printed where it is listening, inherited sockets included
+        ctx.log("HelloWorld from a plugin.");
+        ctx.state().set("helloworld.started", Instant::now());
     }
 
-    fn plugin_match(&self, req: &Request) -> bool {
-        req.method == "GET"
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        req.method == "GET" && req.path_only() == PATH
     }
 
-    fn plugin_serve(&self, req: &Request) -> Response {
-        println!("HelloWorld plugin serving a request for the request:");
-        println!("{}", req.raw);
-        println!("{}", req.path);
-
-        Response {
-            body: String::from(
-                "HTTP/1.1 200 OK\r\n\
-Content-Type: text/html; charset=utf-8\r\n\
-\r\n\
-<!DOCTYPE html>\
-<html><body><h1>Hello World Plugin</h1></body></html>",
-            ),
-        }
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response {
+        let served = ctx
+            .state()
+            .update("helloworld.served", 0_u64, |count| {
+                *count += 1;
+                *count
+            })
+            .unwrap_or(0);
+
+        let uptime = ctx
+            .state()
+            .get::<Instant>("helloworld.started")
+            .map(|started| started.elapsed().as_secs())
+            .unwrap_or(0);
+
+        ctx.log(&format!(
+            "serving request #{} for {} {} ({}s after init)",
+            served,
+            req.method,
+            req.path_only(),
+            uptime
+        ));
+
+        let heading = ctx
+            .settings::<HelloWorldSettings>()
+            .map(|settings| settings.heading.as_str())
+            .unwrap_or("Hello World Plugin");
+
+        // Only counted when MiddlewareSession is registered
+        let query = parse_urlencoded(req.query());
+        let visits = req.session.as_ref().map(|session| {
+            if field(&query, "reset").is_some() {
+                session.destroy();
+                return "<p>Session cleared</p>".to_string();
+            }
+            if field(&query, "rotate").is_some() {
+                session.regenerate();
+            }
+
+            let visits = session.get("helloworld.visits").and_then(|v| v.parse().ok()).unwrap_or(0_u64) + 1;
+            session.set("helloworld.visits", &visits.to_string());
+            format!("<p>Visits this session: {}</p>", visits)
+        });
+
+        Response::html(format!(
+            "<!DOCTYPE html>\
+<html><body><h1>{}</h1>{}</body></html>",
+            heading,
+            visits.unwrap_or_default()
+        ))
     }
 }
@@ -0,0 +1,197 @@
+use crate::structs::context::PluginContext;
+use crate::structs::core::{Request, Response};
+use crate::structs::file_cache::FileCache;
+use crate::structs::glob::glob_match;
+use crate::structs::http_date::rfc3339;
+use crate::structs::plugin::Plugin;
+use crate::structs::xml::xml_escape;
+use std::sync::{Arc, Mutex};
+
+const SITEMAP_PATH: &str = "/sitemap.xml";
+const ROBOTS_PATH: &str = "/robots.txt";
+
+pub struct SitemapConfig {
+    /// Globs over cached paths left out of the sitemap, e.g. `/drafts/**`.
+    pub exclude: Vec<String>,
+    /// Paths listed as `Disallow:` for every user agent in `robots.txt`.
+    pub disallow: Vec<String>,
+}
+
+struct Generated {
+    generation: u64,
+    sitemap: Arc<str>,
+    robots: Arc<str>,
+}
+
+/// Serves `sitemap.xml` from the HTML files in `FileCache` and a `robots.txt` pointing at it.
+/// Both are rebuilt the first time they are requested after a cache reload.
+pub struct PluginSitemap {
+    config: SitemapConfig,
+    generated: Mutex<Option<Generated>>,
+}
+
+impl PluginSitemap {
+    pub fn new(config: SitemapConfig) -> Self {
+        Self {
+            config,
+            generated: Mutex::new(None),
+        }
+    }
+
+    fn current(&self, ctx: &PluginContext) -> Option<(Arc<str>, Arc<str>)> {
+        let cache = ctx.cache()?;
+        let mut generated = self.generated.lock().unwrap();
+
+        if let Some(existing) = generated.as_ref()
+            && existing.generation == cache.generation()
+        {
+            return Some((Arc::clone(&existing.sitemap), Arc::clone(&existing.robots)));
+        }
+
+        let base_url = ctx.config().base_url.trim_end_matches('/');
+        let fresh = Generated {
+            generation: cache.generation(),
+            sitemap: self.build_sitemap(&cache, base_url).into(),
+            robots: self.build_robots(base_url).into(),
+        };
+        ctx.log(&format!("regenerated for cache generation {}", fresh.generation));
+
+        let pair = (Arc::clone(&fresh.sitemap), Arc::clone(&fresh.robots));
+        *generated = Some(fresh);
+        Some(pair)
+    }
+
+    fn build_sitemap(&self, cache: &FileCache, base_url: &str) -> String {
+        let mut pages: Vec<&str> = cache
+            .paths()
+            .filter(|path| path.ends_with(".html"))
+            .filter(|path| !self.config.exclude.iter().any(|pattern| glob_match(pattern, path)))
+            .collect();
+        pages.sort_unstable();
+
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+
+        for path in pages {
+            xml.push_str("  <url>\n");
+            xml.push_str(&format!(
+                "    <loc>{}{}</loc>\n",
+                xml_escape(base_url),
+                xml_escape(&encode_path(canonical_path(path)))
+            ));
+            if let Some(modified) = cache.modified(path) {
+                xml.push_str(&format!("    <lastmod>{}</lastmod>\n", rfc3339(modified)));
+            }
+            xml.push_str("  </url>\n");
+        }
+
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    fn build_robots(&self, base_url: &str) -> String {
+        let mut robots = String::from("User-agent: *\n");
+
+        if self.config.disallow.is_empty() {
+            robots.push_str("Disallow:\n");
+        }
+        for path in &self.config.disallow {
+            robots.push_str(&format!("Disallow: {}\n", path));
+        }
+
+        robots.push_str(&format!("\nSitemap: {}{}\n", base_url, SITEMAP_PATH));
+        robots
+    }
+}
+
+impl Plugin for PluginSitemap {
+    fn plugin_name(&self) -> &str {
+        "Sitemap"
+    }
+
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        req.path == SITEMAP_PATH || req.path == ROBOTS_PATH
+    }
+
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response {
+        let Some((sitemap, robots)) = self.current(ctx) else {
+            return Response::new(503);
+        };
+
+        if req.path == SITEMAP_PATH {
+            Response::new(200)
+                .with_header("Content-Type", "application/xml; charset=utf-8")
+                .with_body(sitemap.as_ref())
+        } else {
+            Response::new(200)
+                .with_header("Content-Type", "text/plain; charset=utf-8")
+                .with_body(robots.as_ref())
+        }
+    }
+}
+
+/// `/index.html` and `/docs/index.html` are listed as `/` and `/docs/`.
+fn canonical_path(path: &str) -> &str {
+    path.strip_suffix("index.html").unwrap_or(path)
+}
+
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sitemap(exclude: &[&str], disallow: &[&str]) -> PluginSitemap {
+        PluginSitemap::new(SitemapConfig {
+            exclude: exclude.iter().map(|pattern| pattern.to_string()).collect(),
+            disallow: disallow.iter().map(|path| path.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn sitemap_lists_html_pages_outside_exclusions() {
+        let cache = FileCache::from_files(&[
+            ("/index.html", ""),
+            ("/docs/index.html", ""),
+            ("/docs/a b.html", ""),
+            ("/drafts/next.html", ""),
+            ("/site.css", ""),
+        ]);
+
+        let xml = sitemap(&["/drafts/**"], &[]).build_sitemap(&cache, "https://example.com");
+
+        let locs: Vec<&str> = xml
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<loc>")?.strip_suffix("</loc>"))
+            .collect();
+        assert_eq!(
+            locs,
+            ["https://example.com/docs/a%20b.html", "https://example.com/docs/", "https://example.com/"]
+        );
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset"));
+        assert!(xml.ends_with("</urlset>\n"));
+    }
+
+    #[test]
+    fn robots_disallows_configured_paths_and_points_at_the_sitemap() {
+        assert_eq!(
+            sitemap(&[], &[]).build_robots("https://example.com"),
+            "User-agent: *\nDisallow:\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+        assert_eq!(
+            sitemap(&[], &["/admin/", "/drafts/"]).build_robots("https://example.com"),
+            "User-agent: *\nDisallow: /admin/\nDisallow: /drafts/\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+    }
+}
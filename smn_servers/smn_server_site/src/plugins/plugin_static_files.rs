@@ -1,40 +1,323 @@
+use crate::structs::context::PluginContext;
 use crate::structs::core::{Request, Response};
 use crate::structs::file_cache::FileCache;
+use crate::structs::glob::glob_match;
+use crate::structs::http_date::http_date;
+use crate::structs::language::{accepted_languages, split_language_prefix, tag_matches, variant_path};
+use crate::structs::mime::content_type_for;
 use crate::structs::plugin::Plugin;
+use std::time::{Duration, SystemTime};
+
+/// Static content is read-only.
+const ALLOW: &str = "GET, HEAD, OPTIONS";
+
+/// Sent for fingerprinted names ahead of any `CacheRule`, since their contents never change.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
 
 pub struct PluginStaticFile;
 
+/// Which files a `CacheRule` covers.
+pub enum CacheMatch {
+    /// Glob over the URL path, e.g. `/assets/**`.
+    Glob(String),
+    /// File extension without the dot, e.g. `html`.
+    Extension(String),
+}
+
+pub struct CacheRule {
+    pub matcher: CacheMatch,
+    pub cache_control: String,
+    /// Also sends `Expires` this far in the future, for clients that ignore `Cache-Control`.
+    pub expires_in: Option<Duration>,
+}
+
+impl CacheRule {
+    fn applies_to(&self, path: &str) -> bool {
+        match &self.matcher {
+            CacheMatch::Glob(pattern) => glob_match(pattern, path),
+            CacheMatch::Extension(ext) => path
+                .rsplit_once('.')
+                .is_some_and(|(_, file_ext)| file_ext.eq_ignore_ascii_case(ext)),
+        }
+    }
+}
+
+/// Translated pages sit next to the original as `/about.fr.html` or under a language
+/// directory as `/fr/about.html`.
+pub struct LanguageSettings {
+    /// Language of the untranslated files, e.g. `en`.
+    pub default: String,
+    /// Languages pages may be translated into, e.g. `fr` or `pt-br`.
+    pub translations: Vec<String>,
+    /// Cookie holding a visitor's chosen language; it outranks `Accept-Language`.
+    pub cookie_name: String,
+}
+
+/// Registered under "StaticFile" in `ServerConfig::plugin_settings`.
+pub struct StaticFileSettings {
+    /// Checked in order; the first matching rule sets the caching headers.
+    pub cache_rules: Vec<CacheRule>,
+    /// `None` serves every path exactly as requested.
+    pub languages: Option<LanguageSettings>,
+}
+
+/// The file chosen for a request and the language it is in.
+struct Negotiated {
+    path: String,
+    language: Option<String>,
+    /// The choice depended on `Accept-Language` or the language cookie.
+    varies: bool,
+}
+
+impl LanguageSettings {
+    fn offers(&self, language: &str) -> Option<&str> {
+        std::iter::once(&self.default)
+            .chain(&self.translations)
+            .find(|offered| offered.eq_ignore_ascii_case(language))
+            .map(String::as_str)
+    }
+
+    /// The cookie's language, then `Accept-Language` in quality order.
+    fn preferences(&self, req: &Request) -> Vec<String> {
+        let mut preferred: Vec<String> = req
+            .cookie(&self.cookie_name)
+            .and_then(|language| self.offers(language))
+            .map(str::to_string)
+            .into_iter()
+            .collect();
+
+        for tag in accepted_languages(req.header("Accept-Language").unwrap_or("")) {
+            let offered = std::iter::once(&self.default)
+                .chain(&self.translations)
+                .find(|language| tag_matches(&tag, language));
+            if let Some(language) = offered {
+                preferred.push(language.clone());
+            }
+        }
+
+        preferred
+    }
+
+    fn negotiate(&self, cache: &FileCache, req: &Request, path: &str) -> Negotiated {
+        let exact = |path: String, language: &str| Negotiated {
+            path,
+            language: Some(language.to_string()),
+            varies: false,
+        };
+
+        // An explicit `/fr/...` prefix wins over any preference
+        if let Some((tag, rest)) = split_language_prefix(path)
+            && let Some(language) = self.offers(tag)
+        {
+            if cache.file(path).is_some() {
+                return exact(path.to_string(), language);
+            }
+            return exact(variant_path(&index_path(rest), language), language);
+        }
+
+        let translated = |language: &str| {
+            [variant_path(path, language), format!("/{}{}", language, path)]
+                .into_iter()
+                .find(|candidate| cache.file(candidate).is_some())
+        };
+
+        if !self.translations.iter().any(|language| translated(language).is_some()) {
+            return Negotiated {
+                path: path.to_string(),
+                language: None,
+                varies: false,
+            };
+        }
+
+        let chosen = self
+            .preferences(req)
+            .into_iter()
+            .take_while(|language| *language != self.default)
+            .find_map(|language| translated(&language).map(|path| (path, language)));
+
+        let (path, language) = chosen.unwrap_or((path.to_string(), self.default.clone()));
+        Negotiated {
+            path,
+            language: Some(language),
+            varies: true,
+        }
+    }
+}
+
 impl Plugin for PluginStaticFile {
     fn plugin_name(&self) -> &str {
         "StaticFile"
     }
 
-    fn plugin_match(&self, req: &Request) -> bool {
-        let path = if req.path == "/" {
-            "/index.html"
-        } else {
-            req.path.as_str()
+    fn plugin_match(&self, req: &Request, ctx: &PluginContext) -> bool {
+        // Rewritten references keep their query strings, e.g. `/app.3f9a1c2b.js?v=1`
+        let path = match req.path_only() {
+            "/" => "/index.html",
+            path => path,
         };
-        FileCache::get(path).is_some()
+
+        let Some(cache) = ctx.cache() else {
+            return false;
+        };
+
+        // `/fr/about.html` may be served from `/about.fr.html`
+        cache.file(path).is_some()
+            || split_language_prefix(path)
+                .is_some_and(|(language, rest)| cache.file(&variant_path(&index_path(rest), language)).is_some())
     }
 
-    fn plugin_serve(&self, req: &Request) -> Response {
-        let path = if req.path == "/" {
-            "/index.html"
-        } else {
-            req.path.as_str()
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response {
+        match req.method.as_str() {
+            "GET" => {}
+            "OPTIONS" => return Response::new(204).with_header("Allow", ALLOW),
+            _ => return Response::new(405).with_header("Allow", ALLOW),
+        }
+
+        // Rewritten references keep their query strings, e.g. `/app.3f9a1c2b.js?v=1`
+        let path = match req.path_only() {
+            "/" => "/index.html",
+            path => path,
         };
 
-        let file = FileCache::get(path).unwrap_or("<h1>File not found</h1>");
+        let Some(cache) = ctx.cache() else {
+            return not_found();
+        };
+
+        let settings = ctx.settings::<StaticFileSettings>();
+        let negotiated = match settings.and_then(|settings| settings.languages.as_ref()) {
+            Some(languages) => languages.negotiate(&cache, req, path),
+            None => Negotiated {
+                path: path.to_string(),
+                language: None,
+                varies: false,
+            },
+        };
+        let path = negotiated.path.as_str();
+
+        match cache.file(path) {
+            Some(file) => {
+                let mut resp = Response::new(200)
+                    .with_header("Content-Type", content_type_for(path))
+                    .with_body(file.as_ref());
+
+                if let Some(language) = &negotiated.language {
+                    resp.set_header("Content-Language", language);
+                }
+                if negotiated.varies {
+                    resp.set_header("Vary", "Accept-Language, Cookie");
+                }
+
+                if cache.is_fingerprinted(path) {
+                    resp.set_header("Cache-Control", IMMUTABLE);
+                    return resp;
+                }
+
+                let rule = settings.and_then(|settings| settings.cache_rules.iter().find(|rule| rule.applies_to(path)));
+
+                if let Some(rule) = rule {
+                    resp.set_header("Cache-Control", &rule.cache_control);
+
+                    if let Some(expires_in) = rule.expires_in {
+                        resp.set_header("Expires", &http_date(SystemTime::now() + expires_in));
+                    }
+                }
+
+                resp
+            }
+            None => not_found(),
+        }
+    }
+}
+
+/// `/fr/` names the language's home page.
+fn index_path(path: &str) -> String {
+    if path.ends_with('/') {
+        format!("{}index.html", path)
+    } else {
+        path.to_string()
+    }
+}
+
+fn not_found() -> Response {
+    Response::new(404)
+        .with_header("Content-Type", "text/html; charset=utf-8")
+        .with_body("<h1>File not found</h1>")
+}
 
-        Response {
-            body: format!(
-                "HTTP/1.1 200 OK\r\n\
-Content-Type: text/html; charset=utf-8\r\n\
-\r\n\
-{}",
-                file
-            ),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::structs::config::ServerConfig;
+    use crate::test_support::{RequestBuilder, send};
+
+    fn languages() -> LanguageSettings {
+        LanguageSettings {
+            default: "en".to_string(),
+            translations: vec!["fr".to_string(), "de".to_string()],
+            cookie_name: "lang".to_string(),
         }
     }
+
+    fn cache() -> FileCache {
+        FileCache::from_files(&[
+            ("/about.html", "about"),
+            ("/about.fr.html", "à propos"),
+            ("/de/about.html", "über"),
+            ("/contact.html", "contact"),
+        ])
+    }
+
+    fn negotiate(req: RequestBuilder, path: &str) -> (String, Option<String>, bool) {
+        let negotiated = languages().negotiate(&cache(), &req.build(), path);
+        (negotiated.path, negotiated.language, negotiated.varies)
+    }
+
+    fn chose(path: &str, language: &str, varies: bool) -> (String, Option<String>, bool) {
+        (path.to_string(), Some(language.to_string()), varies)
+    }
+
+    #[test]
+    fn language_prefix_wins_over_preferences() {
+        let req = || RequestBuilder::get("/").header("Accept-Language", "de").header("Cookie", "lang=de");
+
+        assert_eq!(negotiate(req(), "/fr/about.html"), chose("/about.fr.html", "fr", false));
+        assert_eq!(negotiate(req(), "/de/about.html"), chose("/de/about.html", "de", false));
+    }
+
+    #[test]
+    fn cookie_outranks_accept_language() {
+        let req = RequestBuilder::get("/about.html")
+            .header("Accept-Language", "fr")
+            .header("Cookie", "lang=de");
+
+        assert_eq!(negotiate(req, "/about.html"), chose("/de/about.html", "de", true));
+    }
+
+    #[test]
+    fn accept_language_picks_a_translation_until_the_default() {
+        let req = |header: &str| RequestBuilder::get("/about.html").header("Accept-Language", header);
+
+        assert_eq!(negotiate(req("fr-CA, de;q=0.5"), "/about.html"), chose("/about.fr.html", "fr", true));
+        assert_eq!(negotiate(req("es, de;q=0.8"), "/about.html"), chose("/de/about.html", "de", true));
+        assert_eq!(negotiate(req("en, fr;q=0.8"), "/about.html"), chose("/about.html", "en", true));
+    }
+
+    #[test]
+    fn matches_and_serves_from_the_server_cache() {
+        let server = Server::new(ServerConfig::default(), Vec::new(), vec![PluginStaticFile.into()]);
+        send(&server, RequestBuilder::get("/about.html")).assert_status(404);
+
+        server.cache().install(cache());
+        send(&server, RequestBuilder::get("/about.html"))
+            .assert_status(200)
+            .assert_body_contains("about");
+    }
+
+    #[test]
+    fn untranslated_pages_do_not_vary() {
+        let req = RequestBuilder::get("/contact.html").header("Accept-Language", "fr");
+
+        assert_eq!(negotiate(req, "/contact.html"), ("/contact.html".to_string(), None, false));
+    }
 }
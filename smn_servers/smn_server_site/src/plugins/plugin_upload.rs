@@ -0,0 +1,233 @@
+use crate::structs::context::PluginContext;
+use crate::structs::core::{Request, Response};
+use crate::structs::json::json_escape;
+use crate::structs::plugin::Plugin;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const MAX_FILE_NAME_LEN: usize = 100;
+
+pub struct UploadConfig {
+    /// Serves the upload form on GET and accepts `multipart/form-data` on POST.
+    pub path: String,
+    /// Uploaded files are stored here, flat, under sanitized names.
+    pub upload_dir: String,
+}
+
+/// Stores files from multipart uploads; size limits come from `ServerConfig::multipart`.
+pub struct PluginUpload {
+    config: UploadConfig,
+}
+
+impl PluginUpload {
+    pub fn new(config: UploadConfig) -> Self {
+        Self { config }
+    }
+
+    /// Reserves a free name in the upload directory, adding `-1`, `-2`, ... on clashes.
+    fn reserve(&self, file_name: &str) -> std::io::Result<PathBuf> {
+        let dir = Path::new(&self.config.upload_dir);
+        let (stem, ext) = match file_name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+            _ => (file_name, String::new()),
+        };
+
+        for attempt in 0.. {
+            let candidate = match attempt {
+                0 => dir.join(file_name),
+                n => dir.join(format!("{}-{}{}", stem, n, ext)),
+            };
+
+            match OpenOptions::new().write(true).create_new(true).open(&candidate) {
+                Ok(_) => return Ok(candidate),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!()
+    }
+
+    fn form(&self) -> Response {
+        Response::html(format!(
+            "<!DOCTYPE html>\
+<html><body><h1>Upload</h1>\
+<form method=\"post\" action=\"{}\" enctype=\"multipart/form-data\">\
+<p><input type=\"file\" name=\"file\" multiple required></p>\
+<p><button type=\"submit\">Upload</button></p>\
+</form></body></html>",
+            self.config.path
+        ))
+    }
+}
+
+impl Plugin for PluginUpload {
+    fn plugin_name(&self) -> &str {
+        "Upload"
+    }
+
+    fn plugin_init(&self, ctx: &PluginContext) {
+        if let Err(e) = fs::create_dir_all(&self.config.upload_dir) {
+            ctx.log(&format!("cannot create {}: {}", self.config.upload_dir, e));
+        }
+    }
+
+    fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+        req.path == self.config.path
+    }
+
+    fn plugin_accepts_multipart(&self, req: &Request) -> bool {
+        req.method == "POST"
+    }
+
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response {
+        match req.method.as_str() {
+            "GET" => return self.form(),
+            "POST" => {}
+            "OPTIONS" => return Response::new(204).with_header("Allow", "GET, HEAD, POST, OPTIONS"),
+            _ => return Response::new(405).with_header("Allow", "GET, HEAD, POST, OPTIONS"),
+        }
+
+        let Some(form) = &req.multipart else {
+            return Response::new(415);
+        };
+
+        let mut stored = Vec::new();
+
+        for file in &form.files {
+            // Browsers send an empty part when no file was picked
+            if file.file_name.is_empty() && file.size == 0 {
+                continue;
+            }
+
+            let dest = match self.reserve(&sanitize_file_name(&file.file_name)) {
+                Ok(dest) => dest,
+                Err(e) => {
+                    ctx.log(&format!("cannot store upload: {}", e));
+                    return Response::new(500);
+                }
+            };
+
+            if let Err(e) = file.persist(&dest) {
+                ctx.log(&format!("cannot write {}: {}", dest.display(), e));
+                let _ = fs::remove_file(&dest);
+                return Response::new(500);
+            }
+
+            let name = dest.file_name().unwrap_or_default().to_string_lossy().to_string();
+            ctx.log(&format!("stored {} ({} bytes, {})", name, file.size, file.content_type));
+            stored.push(format!(
+                "{{\"field\":\"{}\",\"name\":\"{}\",\"size\":{}}}",
+                json_escape(&file.field),
+                json_escape(&name),
+                file.size
+            ));
+        }
+
+        if stored.is_empty() {
+            return Response::new(400);
+        }
+
+        Response::new(201)
+            .with_header("Content-Type", "application/json")
+            .with_body(format!("{{\"stored\":[{}]}}", stored.join(",")))
+    }
+}
+
+/// Keeps the base name only, restricted to `[A-Za-z0-9._-]`, with no leading dots.
+fn sanitize_file_name(raw: &str) -> String {
+    let base = raw.rsplit(['/', '\\']).next().unwrap_or("");
+
+    let cleaned: String = base
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+
+    let trimmed = cleaned.trim_start_matches('.');
+    let name: String = trimmed.chars().take(MAX_FILE_NAME_LEN).collect();
+
+    if name.is_empty() {
+        "upload".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::structs::config::ServerConfig;
+    use crate::test_support::{RequestBuilder, ScratchDir, send};
+
+    #[test]
+    fn file_names_are_sanitized() {
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("C:\\Users\\me\\report final.pdf"), "report_final.pdf");
+        assert_eq!(sanitize_file_name("..hidden"), "hidden");
+        assert_eq!(sanitize_file_name("../"), "upload");
+    }
+
+    #[test]
+    fn uploads_are_stored_without_overwriting() {
+        let upload_dir = ScratchDir::new("upload-test");
+
+        let mut config = ServerConfig::default();
+        // Force the second file through the temp-file path
+        config.multipart.spool_threshold = 4;
+        config.multipart.spool_dir = upload_dir.path().to_path_buf();
+
+        let plugin = PluginUpload::new(UploadConfig {
+            path: "/upload".to_string(),
+            upload_dir: upload_dir.path().to_string_lossy().to_string(),
+        });
+        let server = Server::new(config, Vec::new(), vec![plugin.into()]);
+
+        let req = RequestBuilder::post("/upload").multipart(&[
+            ("file", Some("../notes.txt"), b"abc"),
+            ("file", Some("notes.txt"), b"spooled contents"),
+        ]);
+
+        send(&server, req)
+            .assert_status(201)
+            .assert_body_contains(r#""name":"notes.txt""#)
+            .assert_body_contains(r#""name":"notes-1.txt""#);
+
+        assert_eq!(fs::read(upload_dir.join("notes.txt")).unwrap(), b"abc");
+        assert_eq!(fs::read(upload_dir.join("notes-1.txt")).unwrap(), b"spooled contents");
+
+        // Spool files are moved or removed, never left behind
+        let leftovers = fs::read_dir(upload_dir.path())
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("smn-upload-"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn files_over_the_limit_are_refused() {
+        let upload_dir = ScratchDir::new("upload-limit");
+
+        let mut config = ServerConfig::default();
+        config.multipart.max_file_size = 8;
+        config.multipart.spool_threshold = 4;
+        config.multipart.spool_dir = upload_dir.path().to_path_buf();
+
+        let plugin = PluginUpload::new(UploadConfig {
+            path: "/upload".to_string(),
+            upload_dir: upload_dir.path().to_string_lossy().to_string(),
+        });
+        let server = Server::new(config, Vec::new(), vec![plugin.into()]);
+
+        let req = RequestBuilder::post("/upload").multipart(&[("file", Some("big.bin"), &[0_u8; 64])]);
+        send(&server, req).assert_status(413);
+
+        // Neither the file nor its partial spool file is kept
+        assert_eq!(fs::read_dir(upload_dir.path()).unwrap().count(), 0);
+    }
+}
@@ -1,61 +1,896 @@
-use crate::structs::file_cache::FileCache;
-use crate::structs::core::Request;
-use crate::structs::plugin::Plugin;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
+use crate::structs::client_ip::{is_trusted, resolve_client_ip, resolve_scheme};
+use crate::structs::config::ServerConfig;
+use crate::structs::context::{PluginContext, StateStore};
+use crate::structs::file_cache::CacheHandle;
+use crate::structs::core::{Request, Response};
+use crate::structs::health;
+use crate::structs::listener::{Connection, Listener};
+use crate::structs::live_reload::{LIVE_RELOAD_PATH, LiveReload};
+use crate::structs::metrics::{ConnectionGuard, Metrics};
+use crate::structs::middleware::Middleware;
+use crate::structs::multipart::MultipartParser;
+use crate::structs::plugin::PluginEntry;
+use crate::structs::rate_limit::{ConnectionLimiter, ConnectionSlot, RateLimiter};
+use crate::structs::websocket::{self, WebSocket, WebSocketHandler};
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "async"))]
+use crate::structs::listener::accept;
+#[cfg(not(feature = "async"))]
+use std::io::Read;
+
+/// Upper bound on the request line plus headers.
+pub const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Upper bound on a buffered request body; multipart bodies use `MultipartLimits`.
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// The request line and headers must arrive within this long of connecting.
+pub const HEAD_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Longest a single read of the request may wait for data.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Methods plugins are asked to serve; `HEAD` rides on `GET` and `OPTIONS` is answered here.
+const METHODS: [&str; 2] = ["GET", "POST"];
 
 pub struct Server {
-    port: String,
-    plugins: Vec<Box<dyn Plugin>>,
-    file_root: String,
+    config: Arc<ServerConfig>,
+    middlewares: Vec<Box<dyn Middleware>>,
+    pub(crate) plugins: Vec<(PluginEntry, PluginContext)>,
+    cache: CacheHandle,
+    live_reload: Option<Arc<LiveReload>>,
+    pub(crate) metrics: Arc<Metrics>,
+    rate_limiter: RateLimiter,
+    connections: ConnectionLimiter,
+}
+
+/// Held for as long as a connection is being served.
+pub(crate) type ConnectionGuards = (ConnectionGuard, ConnectionSlot);
+
+/// A takeover found by `serve_stream`, waiting for the socket to run on.
+#[cfg(not(feature = "async"))]
+pub(crate) struct PendingTakeover {
+    pub(crate) takeover: Takeover,
+    pub(crate) req: Request,
+    pub(crate) guards: ConnectionGuards,
+    pub(crate) started: Instant,
+}
+
+/// Requests that take the connection over instead of getting a single response.
+pub(crate) enum Takeover {
+    LiveReload(Arc<LiveReload>),
+    WebSocket(String, WebSocketHandler),
 }
 
 impl Server {
-    pub fn new(port: &str, plugins: Vec<Box<dyn Plugin>>, file_root: &str) -> Self {
+    pub fn new(
+        config: ServerConfig,
+        middlewares: Vec<Box<dyn Middleware>>,
+        plugins: Vec<PluginEntry>,
+    ) -> Self {
+        let config = Arc::new(config);
+        let state = Arc::new(StateStore::default());
+        let cache = CacheHandle::default();
+
+        // Each plugin gets its own context so settings and logs are scoped to it
+        let plugins = plugins
+            .into_iter()
+            .map(|plugin| {
+                let ctx = PluginContext::new(plugin.name(), Arc::clone(&config), Arc::clone(&state), cache.clone());
+                (plugin, ctx)
+            })
+            .collect();
+
         Self {
-            port: port.to_string(),
+            rate_limiter: RateLimiter::new(&config.rate_limit),
+            connections: ConnectionLimiter::new(&config.rate_limit),
+            config,
+            middlewares,
             plugins,
-            file_root: file_root.to_string(),
+            cache,
+            live_reload: None,
+            metrics: Arc::new(Metrics::default()),
         }
     }
 
-    pub fn run(&self) {
+    /// The file cache every plugin context reads from.
+    #[cfg(test)]
+    pub(crate) fn cache(&self) -> &CacheHandle {
+        &self.cache
+    }
+
+    /// Loads the file cache and starts the dev-mode watcher; shared by both backends.
+    pub(crate) fn start(&mut self) {
         println!("Initializing file cache...");
-        FileCache::init(&self.file_root);
+        #[cfg(feature = "embed")]
+        println!("Using embedded static files, overridden by {}", self.config.file_root);
+        self.cache.init(&self.config.file_root, &self.config.assets);
+
+        if self.config.dev_mode {
+            println!("Dev mode: caching disabled, watching {}", self.config.file_root);
+            self.live_reload = Some(LiveReload::start(&self.config.file_root, self.cache.clone()));
+        }
+    }
+
+    /// Logs the metrics path and middleware, and initializes plugins once the listeners are up.
+    pub(crate) fn announce(&self) {
+        if self.config.metrics.enabled {
+            println!("Metrics exposed on {}", self.config.metrics.path);
+        }
+
+        for middleware in &self.middlewares {
+            println!("Loaded middleware: {}", middleware.middleware_name());
+        }
+
+        for (plugin, ctx) in &self.plugins {
+            plugin.init(ctx);
+            println!("Loaded plugin: {}", plugin.name());
+        }
+    }
+
+    /// The sockets passed in by socket activation, or else `config.listen` bound afresh.
+    pub(crate) fn listeners(&self) -> Vec<Listener> {
+        let listeners = Listener::activated_or_bind(&self.config.listen)
+            .unwrap_or_else(|e| panic!("Failed to bind to {}", e));
+
+        for listener in &listeners {
+            println!("Serving on {}", listener);
+        }
+
+        listeners
+    }
 
-        let bind_addr = format!("127.0.0.1:{}", self.port);
-        let listener = TcpListener::bind(&bind_addr)
-            .unwrap_or_else(|_| panic!("Failed to bind to {}", bind_addr));
+    #[cfg(not(feature = "async"))]
+    pub fn run(mut self) {
+        self.start();
 
-        println!("Serving on http://{}", bind_addr);
+        let listeners = self.listeners();
+        self.announce();
 
-        for plugin in &self.plugins {
-            plugin.plugin_init();
-            println!("Loaded plugin: {}", plugin.plugin_name());
+        let server = Arc::new(self);
+
+        let accepting: Vec<_> = listeners
+            .into_iter()
+            .map(|listener| {
+                let server = Arc::clone(&server);
+                thread::spawn(move || server.accept_loop(listener))
+            })
+            .collect();
+
+        for handle in accepting {
+            let _ = handle.join();
+        }
+    }
+
+    /// One thread per connection keeps a slow plugin from stalling other clients.
+    #[cfg(not(feature = "async"))]
+    fn accept_loop(self: Arc<Self>, listener: Listener) {
+        loop {
+            let Ok(stream) = accept(&listener) else {
+                continue;
+            };
+
+            let server = Arc::clone(&self);
+            thread::spawn(move || server.handle_client(stream));
         }
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn handle_client(&self, mut stream: Connection) {
+        let peer = stream.peer_addr();
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+
+        if let Some(pending) = self.serve_stream(&mut stream, peer) {
+            // Upgraded connections may sit idle for as long as the client likes
+            let _ = stream.set_read_timeout(None);
+            self.run_takeover(pending.takeover, &pending.req, stream, pending.guards, pending.started);
+        }
+    }
+
+    /// Serves one request over any byte stream. Takeovers need the socket itself,
+    /// so they are handed back to the caller instead of being run.
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn serve_stream<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        peer: Option<SocketAddr>,
+    ) -> Option<PendingTakeover> {
+        let connection = self.metrics.track_connection();
+        let started = Instant::now();
+
+        let slot = match self.accept_slot(peer) {
+            Ok(slot) => slot,
+            Err(resp) => {
+                let _ = stream.write_all(self.refusal("RateLimit", resp, started).as_bytes());
+                return None;
+            }
+        };
+
+        let Some(raw) = read_head(stream, started + HEAD_TIMEOUT) else {
+            let _ = stream.write_all(self.refusal("None", Response::new(408), started).as_bytes());
+            return None;
+        };
 
-        for incoming in listener.incoming() {
-            if let Ok(mut stream) = incoming {
-                self.handle_client(&mut stream);
+        let (mut req, leftover) = self.parse_head(raw, peer);
+
+        let slot = match self.admit(&req, slot) {
+            Ok(slot) => slot,
+            Err((source, resp)) => {
+                let _ = stream.write_all(self.reply(&req, source, resp, started).as_bytes());
+                return None;
             }
+        };
+
+        if let Err(resp) = self.read_request_body(stream, &mut req, leftover) {
+            let _ = stream.write_all(self.reply(&req, "None", resp, started).as_bytes());
+            return None;
+        }
+
+        if let Some(takeover) = self.takeover(&req) {
+            return Some(PendingTakeover {
+                takeover,
+                req,
+                guards: (connection, slot),
+                started,
+            });
         }
+
+        let (source, resp) = self.route(&req);
+        let _ = stream.write_all(self.reply(&req, source, resp, started).as_bytes());
+        None
     }
 
-    fn handle_client(&self, stream: &mut TcpStream) {
-        let mut buffer = [0_u8; 1024];
-        let _ = stream.read(&mut buffer);
+    /// Runs an already-parsed request through admission, middleware, built-in
+    /// routes and plugins, without a connection. Takeovers are not attempted.
+    #[cfg(all(test, not(feature = "async")))]
+    pub(crate) fn handle_request(&self, mut req: Request) -> Response {
+        let started = Instant::now();
+        self.prepare(&mut req);
+        let req = &req;
+
+        let (source, resp) = match self.admit(req, None) {
+            Ok(_slot) => self.route(req),
+            Err(rejected) => rejected,
+        };
+
+        let resp = self.finalize(req, resp);
+        self.metrics
+            .record(source, req.wire_method(), resp.status, started.elapsed(), resp.to_http().len());
+        resp
+    }
 
-        let raw = String::from_utf8_lossy(&buffer).to_string();
-        let req = Request::new(raw);
+    /// Buffers the body, or streams it through the multipart parser.
+    #[cfg(not(feature = "async"))]
+    fn read_request_body<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        req: &mut Request,
+        mut leftover: Vec<u8>,
+    ) -> Result<(), Response> {
+        let (length, parser) = self.body_sink(req)?;
 
-        for plugin in &self.plugins {
-            if plugin.plugin_match(&req) {
-                let resp = plugin.plugin_serve(&req);
-                let _ = stream.write_all(resp.body.as_bytes());
-                return;
+        if length > leftover.len() && req.header_has_token("Expect", "100-continue") {
+            let _ = stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n");
+        }
+
+        let Some(mut parser) = parser else {
+            req.body = read_body(stream, leftover, length).ok_or(Response::new(400))?;
+            return Ok(());
+        };
+
+        leftover.truncate(length);
+        let mut remaining = length - leftover.len();
+        parser.feed(&leftover)?;
+
+        let mut buffer = vec![0_u8; 64 * 1024];
+        while remaining > 0 {
+            let wanted = remaining.min(buffer.len());
+            match stream.read(&mut buffer[..wanted]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    parser.feed(&buffer[..n])?;
+                    remaining -= n;
+                }
             }
         }
 
-        let _ = stream.write_all(b"HTTP/1.1 404 NOT FOUND\r\n\r\n");
+        // A body cut short would otherwise be handed on as if complete
+        if remaining > 0 {
+            return Err(Response::new(400));
+        }
+
+        req.multipart = Some(parser.finish()?);
+        Ok(())
+    }
+
+    /// Built-in routes, then the first matching plugin.
+    #[cfg(not(feature = "async"))]
+    fn route(&self, req: &Request) -> (&str, Response) {
+        if let Some(routed) = self.route_builtin(req) {
+            return routed;
+        }
+
+        for (entry, ctx) in &self.plugins {
+            let PluginEntry::Sync(plugin) = entry;
+
+            if plugin.plugin_match(req, ctx) {
+                return (plugin.plugin_name(), plugin.plugin_serve(req, ctx));
+            }
+        }
+
+        ("None", self.unmatched(req))
+    }
+
+    /// Parses a head read off the connection through `resolve_peer` and `prepare`,
+    /// returning it with any body bytes that arrived in the same reads.
+    pub(crate) fn parse_head(&self, raw: Vec<u8>, peer: Option<SocketAddr>) -> (Request, Vec<u8>) {
+        let (head, leftover) = split_head(raw);
+        let mut req = Request::new(head);
+        self.resolve_peer(&mut req, peer);
+        self.prepare(&mut req);
+        (req, leftover)
+    }
+
+    /// Records the connection's peer and the originating client and scheme, looking
+    /// through trusted proxies. Unix socket peers have no address and count as loopback,
+    /// since only local processes allowed to open the socket file can connect.
+    fn resolve_peer(&self, req: &mut Request, peer: Option<SocketAddr>) {
+        let peer_ip = peer_ip(peer);
+
+        req.peer = peer;
+        req.client_ip = resolve_client_ip(peer_ip, req, &self.config.trusted_proxies);
+        req.scheme = resolve_scheme(peer_ip, req, &self.config.trusted_proxies);
+    }
+
+    /// Serves `HEAD` as `GET`, then runs every `middleware_prepare` hook in registration order.
+    pub(crate) fn prepare(&self, req: &mut Request) {
+        if req.method == "HEAD" {
+            req.method = "GET".to_string();
+            req.head_only = true;
+        }
+
+        for middleware in &self.middlewares {
+            middleware.middleware_prepare(req);
+        }
+    }
+
+    /// Takes the peer's connection slot before anything is read, so slow or idle
+    /// connections count against the cap. Trusted proxies carry many clients at once,
+    /// so their connections are `None` here and capped per client by `admit`.
+    pub(crate) fn accept_slot(&self, peer: Option<SocketAddr>) -> Result<Option<ConnectionSlot>, Response> {
+        let peer_ip = peer_ip(peer);
+
+        if is_trusted(peer_ip, &self.config.trusted_proxies) {
+            return Ok(None);
+        }
+        self.connections.acquire(peer_ip).map(Some)
+    }
+
+    /// Applies the per-client connection cap when `accept_slot` left it to the resolved
+    /// client, then rate limits and `middleware_before` hooks.
+    pub(crate) fn admit(&self, req: &Request, slot: Option<ConnectionSlot>) -> Result<ConnectionSlot, (&str, Response)> {
+        let slot = match slot {
+            Some(slot) => slot,
+            None => self
+                .connections
+                .acquire(req.client_ip)
+                .map_err(|resp| ("RateLimit", resp))?,
+        };
+
+        if let Some(resp) = self.rate_limiter.check(&req.path, req.client_ip) {
+            return Err(("RateLimit", resp));
+        }
+
+        for middleware in &self.middlewares {
+            if let Some(resp) = middleware.middleware_before(req) {
+                return Err((middleware.middleware_name(), resp));
+            }
+        }
+
+        Ok(slot)
+    }
+
+    /// The body length to read and, for `multipart/form-data`, the parser to stream it through.
+    /// Only the plugin that will serve the request can opt in to multipart parsing.
+    pub(crate) fn body_sink(&self, req: &Request) -> Result<(usize, Option<MultipartParser>), Response> {
+        let parser = MultipartParser::for_request(req, &self.config.multipart);
+
+        let accepted = self
+            .plugins
+            .iter()
+            .find(|(entry, ctx)| entry.matches(req, ctx))
+            .is_some_and(|(entry, _)| entry.accepts_multipart(req));
+        if parser.is_some() && !accepted {
+            return Err(Response::new(415));
+        }
+
+        let limit = match parser {
+            Some(_) => self.config.multipart.max_total_size,
+            None => MAX_BODY_SIZE,
+        };
+
+        Ok((body_length(req, limit)?, parser))
+    }
+
+    /// Finds a live-reload subscription or an accepted WebSocket upgrade.
+    pub(crate) fn takeover(&self, req: &Request) -> Option<Takeover> {
+        // HEAD is served as GET, but must never open a stream or an upgrade
+        if req.head_only {
+            return None;
+        }
+
+        if let Some(live_reload) = &self.live_reload
+            && req.path == LIVE_RELOAD_PATH
+        {
+            return Some(Takeover::LiveReload(Arc::clone(live_reload)));
+        }
+
+        if !websocket::is_upgrade_request(req) {
+            return None;
+        }
+
+        self.plugins
+            .iter()
+            .filter(|(plugin, ctx)| plugin.matches(req, ctx))
+            .find_map(|(plugin, ctx)| {
+                plugin
+                    .websocket(req, ctx)
+                    .map(|handler| Takeover::WebSocket(plugin.name().to_string(), handler))
+            })
+    }
+
+    pub(crate) fn run_takeover(
+        &self,
+        takeover: Takeover,
+        req: &Request,
+        mut stream: Connection,
+        guards: ConnectionGuards,
+        started: Instant,
+    ) {
+        match takeover {
+            Takeover::LiveReload(live_reload) => live_reload.subscribe(stream),
+            Takeover::WebSocket(source, handler) => {
+                let (status, reply) = match websocket::handshake_response(req) {
+                    Some(handshake) => (101, handshake),
+                    None => {
+                        let resp = Response::new(400).with_header("Sec-WebSocket-Version", "13");
+                        (400, self.finish(req, resp))
+                    }
+                };
+
+                let written = stream.write_all(reply.as_bytes()).is_ok();
+                self.metrics
+                    .record(&source, req.wire_method(), status, started.elapsed(), reply.len());
+
+                if written && status == 101 {
+                    // Long-lived connection, keep it off the request workers
+                    thread::spawn(move || {
+                        let _guards = guards;
+                        handler(WebSocket::new(stream));
+                    });
+                }
+            }
+        }
+    }
+
+    /// Health probes and the metrics endpoint, answered before any plugin.
+    pub(crate) fn route_builtin(&self, req: &Request) -> Option<(&str, Response)> {
+        if let Some(resp) = health::probe_response(&req.path, self.cache.current().as_deref()) {
+            return Some(("Health", resp));
+        }
+
+        // Scrapers may add a query string, e.g. Prometheus `params`
+        if self.config.metrics.enabled && req.path_only() == self.config.metrics.path {
+            let resp = if self.config.metrics.allows(req.client_ip) {
+                Response::new(200)
+                    .with_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+                    .with_body(self.metrics.render(self.cache.current().as_deref()))
+            } else {
+                Response::new(403)
+            };
+
+            return Some(("Metrics", resp));
+        }
+
+        None
+    }
+
+    /// Applies `middleware_after` hooks and server-wide changes.
+    pub(crate) fn finalize(&self, req: &Request, mut resp: Response) -> Response {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.middleware_after(req, &mut resp);
+        }
+
+        if let Some(live_reload) = &self.live_reload {
+            live_reload.decorate(&mut resp);
+        }
+
+        if req.head_only {
+            let length = resp.header("Content-Length").map_or(resp.body.len().to_string(), str::to_string);
+            resp.set_header("Content-Length", &length);
+            resp.body.clear();
+        }
+
+        resp.set_header("Connection", "close");
+        resp
+    }
+
+    /// No plugin matched: 404, except that `OPTIONS` lists the methods some plugin would
+    /// serve the path under, and `OPTIONS *` those the server supports at all.
+    pub(crate) fn unmatched(&self, req: &Request) -> Response {
+        if req.method != "OPTIONS" {
+            return Response::new(404);
+        }
+
+        if req.path == "*" {
+            return Response::new(204).with_header("Allow", &allow_header(&METHODS));
+        }
+
+        let allowed: Vec<&str> = METHODS
+            .into_iter()
+            .filter(|method| {
+                let probe = req.with_method(method);
+                self.plugins.iter().any(|(entry, ctx)| entry.matches(&probe, ctx))
+            })
+            .collect();
+
+        if allowed.is_empty() {
+            Response::new(404)
+        } else {
+            Response::new(204).with_header("Allow", &allow_header(&allowed))
+        }
+    }
+
+    /// `finalize`, serialized for the wire.
+    pub(crate) fn finish(&self, req: &Request, resp: Response) -> String {
+        self.finalize(req, resp).to_http()
+    }
+
+    /// `finish`, recorded in the metrics as served by `source`.
+    pub(crate) fn reply(&self, req: &Request, source: &str, resp: Response, started: Instant) -> String {
+        let status = resp.status;
+        let http = self.finish(req, resp);
+
+        self.metrics
+            .record(source, req.wire_method(), status, started.elapsed(), http.len());
+        http
+    }
+
+    /// The answer for a connection turned away before it sent a complete request.
+    pub(crate) fn refusal(&self, source: &str, resp: Response, started: Instant) -> String {
+        let status = resp.status;
+        let http = resp.with_header("Connection", "close").to_http();
+
+        self.metrics.record(source, "OTHER", status, started.elapsed(), http.len());
+        http
+    }
+}
+
+/// `methods` plus the ones the server answers for them, e.g. `GET, HEAD, OPTIONS`.
+fn allow_header(methods: &[&str]) -> String {
+    let mut allow = Vec::new();
+    for method in methods {
+        allow.push(*method);
+        if *method == "GET" {
+            allow.push("HEAD");
+        }
+    }
+    allow.push("OPTIONS");
+    allow.join(", ")
+}
+
+/// Unix socket peers have no address and count as loopback.
+fn peer_ip(peer: Option<SocketAddr>) -> IpAddr {
+    peer.map_or(IpAddr::V4(Ipv4Addr::LOCALHOST), |addr| addr.ip())
+}
+
+/// Splits off any body bytes that arrived in the same reads as the headers.
+fn split_head(raw: Vec<u8>) -> (String, Vec<u8>) {
+    match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(end) => (
+            String::from_utf8_lossy(&raw[..end + 4]).to_string(),
+            raw[end + 4..].to_vec(),
+        ),
+        None => (String::from_utf8_lossy(&raw).to_string(), Vec::new()),
+    }
+}
+
+/// The body size to read, from `Content-Length`; chunked and oversized bodies are refused.
+fn body_length(req: &Request, limit: usize) -> Result<usize, Response> {
+    if req.header("Transfer-Encoding").is_some() {
+        return Err(Response::new(411));
+    }
+
+    let Some(value) = req.header("Content-Length") else {
+        return Ok(0);
+    };
+
+    // `parse` would also take a leading `+`
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(Response::new(400));
+    }
+
+    match value.parse::<usize>() {
+        Ok(length) if length > limit => Err(Response::new(413)),
+        Ok(length) => Ok(length),
+        Err(_) => Err(Response::new(400)),
+    }
+}
+
+/// Reads until the blank line ending the headers, or until the size cap is hit. `None`
+/// when a read fails, e.g. on the socket's read timeout, or `deadline` passes first.
+#[cfg(not(feature = "async"))]
+fn read_head<S: Read>(stream: &mut S, deadline: Instant) -> Option<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buffer = [0_u8; 1024];
+
+    while head.len() < MAX_HEAD_SIZE {
+        if Instant::now() >= deadline {
+            return None;
+        }
+
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => head.extend_from_slice(&buffer[..n]),
+            Err(_) => return None,
+        }
+
+        if head.windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+
+    Some(head)
+}
+
+/// Tops up the bytes read with the head to `length`; `None` if the stream ends first.
+#[cfg(not(feature = "async"))]
+fn read_body<S: Read>(stream: &mut S, mut body: Vec<u8>, length: usize) -> Option<Vec<u8>> {
+    body.truncate(length);
+
+    let mut buffer = [0_u8; 8192];
+    while body.len() < length {
+        let wanted = (length - body.len()).min(buffer.len());
+        match stream.read(&mut buffer[..wanted]) {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => body.extend_from_slice(&buffer[..n]),
+        }
+    }
+
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::middleware_auth::{AuthConfig, AuthRule, MiddlewareAuth};
+    use crate::plugins::plugin_echo_socket::PluginEchoSocket;
+    use crate::plugins::plugin_helloworld::PluginHelloWorld;
+    use crate::structs::plugin::Plugin;
+    use crate::structs::rate_limit::RateLimitRule;
+    use crate::test_support::{RequestBuilder, TEST_CLIENT, TEST_PEER, dispatch, send, send_from};
+
+    /// Reports what the pipeline handed it.
+    struct PluginInspect;
+
+    impl Plugin for PluginInspect {
+        fn plugin_name(&self) -> &str {
+            "Inspect"
+        }
+
+        fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+            req.path.starts_with("/inspect")
+        }
+
+        fn plugin_accepts_multipart(&self, req: &Request) -> bool {
+            req.path == "/inspect/upload"
+        }
+
+        fn plugin_serve(&self, req: &Request, _ctx: &PluginContext) -> Response {
+            let files = req.multipart.as_ref().map_or(0, |form| form.files.len());
+            Response::new(200).with_body(format!(
+                "{} {} body={} files={}",
+                req.method,
+                req.path,
+                req.body.len(),
+                files
+            ))
+        }
+    }
+
+    /// Serves `GET /mine` to the test client only.
+    struct PluginClientOnly;
+
+    impl Plugin for PluginClientOnly {
+        fn plugin_name(&self) -> &str {
+            "ClientOnly"
+        }
+
+        fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+            req.method == "GET" && req.path == "/mine" && req.client_ip == TEST_CLIENT
+        }
+
+        fn plugin_serve(&self, _req: &Request, _ctx: &PluginContext) -> Response {
+            Response::new(200)
+        }
+    }
+
+    fn server() -> Server {
+        Server::new(ServerConfig::default(), Vec::new(), vec![PluginInspect.into()])
+    }
+
+    #[test]
+    fn unmatched_paths_are_not_found() {
+        send(&server(), RequestBuilder::get("/missing"))
+            .assert_status(404)
+            .assert_header("Connection", "close");
+    }
+
+    #[test]
+    fn head_is_served_as_get_without_a_body() {
+        let resp = send(&server(), RequestBuilder::new("HEAD", "/inspect"));
+
+        resp.assert_status(200)
+            .assert_header("Content-Length", &"GET /inspect body=0 files=0".len().to_string());
+        assert!(resp.body.is_empty());
+    }
+
+    #[test]
+    fn options_lists_methods_of_get_routes() {
+        send(&server(), RequestBuilder::new("OPTIONS", "*"))
+            .assert_status(204)
+            .assert_header("Allow", "GET, HEAD, POST, OPTIONS");
+        send(&server(), RequestBuilder::new("OPTIONS", "/missing")).assert_status(404);
+
+        let hello = Server::new(ServerConfig::default(), Vec::new(), vec![PluginHelloWorld.into()]);
+        send(&hello, RequestBuilder::new("OPTIONS", "/hello"))
+            .assert_status(204)
+            .assert_header("Allow", "GET, HEAD, OPTIONS");
+        send(&hello, RequestBuilder::new("OPTIONS", "/anything")).assert_status(404);
+
+        // The probe is the parsed request, resolved client and all
+        let mine = Server::new(ServerConfig::default(), Vec::new(), vec![PluginClientOnly.into()]);
+        send(&mine, RequestBuilder::new("OPTIONS", "/mine")).assert_status(204);
+    }
+
+    #[test]
+    fn head_never_takes_the_connection_over() {
+        let server = Server::new(ServerConfig::default(), Vec::new(), vec![PluginEchoSocket.into()]);
+        let upgrade = |method: &str| {
+            let mut req = RequestBuilder::new(method, "/ws/echo")
+                .header("Connection", "Upgrade")
+                .header("Upgrade", "websocket")
+                .build();
+            server.prepare(&mut req);
+            server.takeover(&req)
+        };
+
+        assert!(upgrade("GET").is_some());
+        assert!(upgrade("HEAD").is_none());
+    }
+
+    #[test]
+    fn health_probe_is_answered_before_plugins() {
+        send(&server(), RequestBuilder::get("/healthz")).assert_status(200);
+    }
+
+    #[test]
+    fn metrics_allowlist_ignores_spoofed_forwarding_headers() {
+        let spoofed = || {
+            RequestBuilder::get("/metrics")
+                .header("X-Forwarded-For", "127.0.0.1")
+                .header("X-Real-IP", "127.0.0.1")
+        };
+        send(&server(), spoofed()).assert_status(403);
+
+        let proxy = SocketAddr::from(([127, 0, 0, 1], 40000));
+        send_from(&server(), proxy, RequestBuilder::get("/metrics")).assert_status(200);
+        send_from(&server(), proxy, RequestBuilder::get("/metrics?format=text"))
+            .assert_status(200)
+            .assert_body_contains("# TYPE smn_site_requests_total counter");
+        let forwarded = RequestBuilder::get("/metrics").header("X-Forwarded-For", "127.0.0.1, 203.0.113.5");
+        send_from(&server(), proxy, forwarded).assert_status(403);
+    }
+
+    #[test]
+    fn body_is_read_from_the_stream() {
+        send(&server(), RequestBuilder::post("/inspect").body("hello"))
+            .assert_status(200)
+            .assert_body_contains("POST /inspect body=5");
+    }
+
+    #[test]
+    fn short_or_signed_bodies_are_refused() {
+        let truncated = RequestBuilder::post("/inspect").header("Content-Length", "10").body("short");
+        send(&server(), truncated).assert_status(400);
+
+        let signed = RequestBuilder::post("/inspect").header("Content-Length", "+5").body("hello");
+        send(&server(), signed).assert_status(400);
+    }
+
+    #[test]
+    fn oversized_body_is_refused() {
+        let req = RequestBuilder::post("/inspect")
+            .header("Content-Length", &(MAX_BODY_SIZE + 1).to_string());
+
+        send(&server(), req).assert_status(413);
+    }
+
+    #[test]
+    fn multipart_body_is_parsed_while_streaming() {
+        let req = RequestBuilder::post("/inspect/upload").multipart(&[
+            ("note", None, b"hi"),
+            ("file", Some("a.txt"), b"contents"),
+        ]);
+
+        send(&server(), req).assert_status(200).assert_body_contains("files=1");
+    }
+
+    #[test]
+    fn multipart_body_needs_a_plugin_that_opts_in() {
+        let parts: &[(&str, Option<&str>, &[u8])] = &[("file", Some("a.txt"), b"contents")];
+
+        send(&server(), RequestBuilder::post("/inspect").multipart(parts)).assert_status(415);
+        send(&server(), RequestBuilder::post("/missing").multipart(parts)).assert_status(415);
+    }
+
+    #[test]
+    fn rate_limit_rejects_after_burst() {
+        let mut config = ServerConfig::default();
+        config.rate_limit.rules = vec![RateLimitRule {
+            path_prefix: "/".to_string(),
+            burst: 2,
+            per_second: 0.001,
+        }];
+        let server = Server::new(config, Vec::new(), vec![PluginInspect.into()]);
+
+        dispatch(&server, RequestBuilder::get("/inspect")).assert_status(200);
+        dispatch(&server, RequestBuilder::get("/inspect")).assert_status(200);
+        dispatch(&server, RequestBuilder::get("/inspect")).assert_status(429);
+    }
+
+    #[test]
+    fn connection_cap_is_taken_per_peer_before_reading() {
+        let mut config = ServerConfig::default();
+        config.rate_limit.max_connections_per_ip = 1;
+        let server = Server::new(config, Vec::new(), vec![PluginInspect.into()]);
+
+        let held = server.accept_slot(Some(TEST_PEER)).ok().flatten();
+        assert!(held.is_some());
+        send(&server, RequestBuilder::get("/inspect")).assert_status(429);
+
+        // A trusted proxy's connections are capped per forwarded client instead
+        let proxy: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        assert!(matches!(server.accept_slot(Some(proxy)), Ok(None)));
+        send_from(&server, proxy, RequestBuilder::get("/inspect").header("X-Real-IP", "198.51.100.7"))
+            .assert_status(200);
+
+        drop(held);
+        send(&server, RequestBuilder::get("/inspect")).assert_status(200);
+    }
+
+    #[test]
+    fn middleware_can_refuse_before_plugins() {
+        let auth = MiddlewareAuth::new(AuthConfig {
+            credentials_file: "/nonexistent/credentials".to_string(),
+            tokens_file: "/nonexistent/tokens".to_string(),
+            rules: vec![AuthRule {
+                path_prefix: "/inspect/private".to_string(),
+                realm: "Test".to_string(),
+                basic: true,
+                bearer: false,
+            }],
+        });
+        let server = Server::new(ServerConfig::default(), vec![Box::new(auth)], vec![PluginInspect.into()]);
+
+        dispatch(&server, RequestBuilder::get("/inspect/private"))
+            .assert_status(401)
+            .assert_header("WWW-Authenticate", "Basic realm=\"Test\", charset=\"UTF-8\"");
+        dispatch(&server, RequestBuilder::get("/inspect/public")).assert_status(200);
     }
 }
@@ -0,0 +1,285 @@
+use crate::server::{HEAD_TIMEOUT, MAX_HEAD_SIZE, READ_TIMEOUT, Server};
+use crate::structs::core::{Request, Response};
+use crate::structs::listener::{AsyncListener, IntoConnection};
+use crate::structs::plugin::PluginEntry;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::timeout;
+
+/// Tokio backend, enabled with the `async` feature.
+///
+/// Async plugins are awaited on the runtime; sync plugins run through
+/// `block_in_place` so a blocking `plugin_serve` cannot starve other tasks.
+impl Server {
+    pub fn run(mut self) {
+        self.start();
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start tokio runtime");
+
+        runtime.block_on(Arc::new(self).serve());
+    }
+
+    async fn serve(self: Arc<Self>) {
+        let listeners = self.listeners();
+        self.announce();
+
+        let accepting: Vec<_> = listeners
+            .into_iter()
+            .map(|listener| {
+                let listener =
+                    AsyncListener::from_std(listener).expect("Failed to register listener with tokio");
+                tokio::spawn(Arc::clone(&self).accept_loop(listener))
+            })
+            .collect();
+
+        for handle in accepting {
+            let _ = handle.await;
+        }
+    }
+
+    async fn accept_loop(self: Arc<Self>, listener: AsyncListener) {
+        loop {
+            let server = Arc::clone(&self);
+
+            match &listener {
+                AsyncListener::Tcp(listener) => {
+                    let Ok((stream, peer)) = listener.accept().await else {
+                        continue;
+                    };
+                    tokio::spawn(async move { server.handle_client(stream, Some(peer)).await });
+                }
+                #[cfg(unix)]
+                AsyncListener::Unix(listener) => {
+                    let Ok((stream, _)) = listener.accept().await else {
+                        continue;
+                    };
+                    tokio::spawn(async move { server.handle_client(stream, None).await });
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn handle_client<S>(&self, mut stream: S, peer: Option<SocketAddr>)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + IntoConnection,
+    {
+        let connection = self.metrics.track_connection();
+        let started = Instant::now();
+
+        let slot = match self.accept_slot(peer) {
+            Ok(slot) => slot,
+            Err(resp) => return write(&mut stream, self.refusal("RateLimit", resp, started)).await,
+        };
+
+        let Ok(Some(raw)) = timeout(HEAD_TIMEOUT, read_head(&mut stream)).await else {
+            return write(&mut stream, self.refusal("None", Response::new(408), started)).await;
+        };
+
+        let (mut req, leftover) = self.parse_head(raw, peer);
+
+        let slot = match self.admit(&req, slot) {
+            Ok(slot) => slot,
+            Err((source, resp)) => return write(&mut stream, self.reply(&req, source, resp, started)).await,
+        };
+
+        if let Err(resp) = self.read_request_body(&mut stream, &mut req, leftover).await {
+            return write(&mut stream, self.reply(&req, "None", resp, started)).await;
+        }
+
+        if let Some(takeover) = self.takeover(&req) {
+            // Upgraded connections are driven by blocking std handlers
+            if let Ok(stream) = stream.into_connection() {
+                tokio::task::block_in_place(|| {
+                    self.run_takeover(takeover, &req, stream, (connection, slot), started)
+                });
+            }
+            return;
+        }
+
+        let (source, resp) = self.route(&req).await;
+        write(&mut stream, self.reply(&req, source, resp, started)).await;
+    }
+
+    /// Runs an already-parsed request through admission, middleware, built-in
+    /// routes and plugins, without a connection. Takeovers are not attempted.
+    #[cfg(test)]
+    pub(crate) async fn handle_request(&self, mut req: Request) -> Response {
+        let started = Instant::now();
+        self.prepare(&mut req);
+        let req = &req;
+
+        let (source, resp) = match self.admit(req, None) {
+            Ok(_slot) => self.route(req).await,
+            Err(rejected) => rejected,
+        };
+
+        let resp = self.finalize(req, resp);
+        self.metrics
+            .record(source, req.wire_method(), resp.status, started.elapsed(), resp.to_http().len());
+        resp
+    }
+
+    /// Buffers the body, or streams it through the multipart parser.
+    async fn read_request_body(
+        &self,
+        stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+        req: &mut Request,
+        mut leftover: Vec<u8>,
+    ) -> Result<(), Response> {
+        let (length, parser) = self.body_sink(req)?;
+
+        if length > leftover.len() && req.header_has_token("Expect", "100-continue") {
+            let _ = stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await;
+        }
+
+        let Some(mut parser) = parser else {
+            req.body = read_body(stream, leftover, length).await.ok_or(Response::new(400))?;
+            return Ok(());
+        };
+
+        leftover.truncate(length);
+        let mut remaining = length - leftover.len();
+        parser.feed(&leftover)?;
+
+        // Spooling to disk blocks, so feed outside the async scheduler
+        let mut buffer = vec![0_u8; 64 * 1024];
+        while remaining > 0 {
+            let wanted = remaining.min(buffer.len());
+            match read_some(stream, &mut buffer[..wanted]).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    tokio::task::block_in_place(|| parser.feed(&buffer[..n]))?;
+                    remaining -= n;
+                }
+            }
+        }
+
+        // A body cut short would otherwise be handed on as if complete
+        if remaining > 0 {
+            return Err(Response::new(400));
+        }
+
+        req.multipart = Some(parser.finish()?);
+        Ok(())
+    }
+
+    /// Built-in routes, then the first matching plugin.
+    async fn route(&self, req: &Request) -> (&str, Response) {
+        if let Some(routed) = self.route_builtin(req) {
+            return routed;
+        }
+
+        for (entry, ctx) in &self.plugins {
+            if !entry.matches(req, ctx) {
+                continue;
+            }
+
+            let resp = match entry {
+                PluginEntry::Sync(plugin) => {
+                    tokio::task::block_in_place(|| plugin.plugin_serve(req, ctx))
+                }
+                PluginEntry::Async(plugin) => plugin.plugin_serve(req, ctx).await,
+            };
+
+            return (entry.name(), resp);
+        }
+
+        ("None", self.unmatched(req))
+    }
+}
+
+/// Write failures are ignored; the client has gone and the response is already recorded.
+async fn write(stream: &mut (impl AsyncWrite + Unpin), http: String) {
+    let _ = stream.write_all(http.as_bytes()).await;
+}
+
+/// Reads until the blank line ending the headers, or until the size cap is hit. `None`
+/// when a read fails or times out; the caller bounds the whole head with `HEAD_TIMEOUT`.
+async fn read_head(stream: &mut (impl AsyncRead + Unpin)) -> Option<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buffer = [0_u8; 1024];
+
+    while head.len() < MAX_HEAD_SIZE {
+        match read_some(stream, &mut buffer).await {
+            Ok(0) => break,
+            Ok(n) => head.extend_from_slice(&buffer[..n]),
+            Err(_) => return None,
+        }
+
+        if head.windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+
+    Some(head)
+}
+
+/// One read, failing with `TimedOut` if no data arrives within `READ_TIMEOUT`.
+async fn read_some(stream: &mut (impl AsyncRead + Unpin), buffer: &mut [u8]) -> io::Result<usize> {
+    timeout(READ_TIMEOUT, stream.read(buffer))
+        .await
+        .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+}
+
+/// Tops up the bytes read with the head to `length`; `None` if the stream ends first.
+async fn read_body(stream: &mut (impl AsyncRead + Unpin), mut body: Vec<u8>, length: usize) -> Option<Vec<u8>> {
+    body.truncate(length);
+
+    let mut buffer = [0_u8; 8192];
+    while body.len() < length {
+        let wanted = (length - body.len()).min(buffer.len());
+        match read_some(stream, &mut buffer[..wanted]).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => body.extend_from_slice(&buffer[..n]),
+        }
+    }
+
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::Server;
+    use crate::structs::config::ServerConfig;
+    use crate::structs::context::PluginContext;
+    use crate::structs::core::{Request, Response};
+    use crate::structs::plugin::{AsyncPlugin, PluginEntry, PluginFuture};
+    use crate::test_support::{RequestBuilder, send};
+
+    /// Answers after yielding to the runtime, with the body it was sent.
+    struct PluginAsyncEcho;
+
+    impl AsyncPlugin for PluginAsyncEcho {
+        fn plugin_name(&self) -> &str {
+            "AsyncEcho"
+        }
+
+        fn plugin_match(&self, req: &Request, _ctx: &PluginContext) -> bool {
+            req.path == "/echo"
+        }
+
+        fn plugin_serve<'a>(&'a self, req: &'a Request, _ctx: &'a PluginContext) -> PluginFuture<'a> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                Response::new(200).with_body(format!("echo {}", String::from_utf8_lossy(&req.body)))
+            })
+        }
+    }
+
+    #[test]
+    fn async_plugins_are_awaited() {
+        let server = Server::new(ServerConfig::default(), Vec::new(), vec![PluginEntry::from_async(PluginAsyncEcho)]);
+
+        send(&server, RequestBuilder::post("/echo").body("hi"))
+            .assert_status(200)
+            .assert_body_contains("echo hi");
+        send(&server, RequestBuilder::new("HEAD", "/echo")).assert_status(200);
+        send(&server, RequestBuilder::get("/missing")).assert_status(404);
+    }
+}
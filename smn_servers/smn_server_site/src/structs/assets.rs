@@ -0,0 +1,324 @@
+//! Build steps applied to `FileCache` contents each time the cache is loaded.
+
+use crate::structs::glob::glob_match;
+use crate::structs::minify;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Hex digits of the content hash put into fingerprinted names.
+const HASH_LEN: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct AssetConfig {
+    /// Strip comments and redundant whitespace from HTML, CSS and JS. Runs before
+    /// fingerprinting, so hashes cover what is actually served.
+    pub minify: bool,
+    /// Globs over cached paths left exactly as authored. Files named `*.min.css` or
+    /// `*.min.js` are always skipped.
+    pub minify_exclude: Vec<String>,
+    /// Serve assets under content-hashed names such as `/app.3f9a1c2b.css` and rewrite
+    /// references in HTML and CSS to them. The original paths keep working.
+    pub fingerprint: bool,
+    /// Extensions, without the dot, of files that get fingerprinted names.
+    pub fingerprint_extensions: Vec<String>,
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        Self {
+            minify: false,
+            minify_exclude: Vec::new(),
+            fingerprint: true,
+            fingerprint_extensions: ["css", "js", "mjs", "svg"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// What a pipeline run did, beyond rewriting the files in place.
+#[derive(Default)]
+pub struct AssetReport {
+    /// Fingerprinted name of each asset, keyed by its original path.
+    pub fingerprints: HashMap<String, String>,
+    pub minified_files: usize,
+    pub bytes_saved: usize,
+}
+
+/// Runs the pipeline over freshly read files, rewriting them in place.
+pub fn process(files: &mut HashMap<String, Arc<str>>, config: &AssetConfig) -> AssetReport {
+    let mut report = AssetReport::default();
+
+    if config.minify {
+        minify_files(files, config, &mut report);
+    }
+    if config.fingerprint {
+        report.fingerprints = fingerprint(files, config);
+    }
+
+    report
+}
+
+fn minify_files(files: &mut HashMap<String, Arc<str>>, config: &AssetConfig, report: &mut AssetReport) {
+    for (path, contents) in files.iter_mut() {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".min.css")
+            || lower.ends_with(".min.js")
+            || config.minify_exclude.iter().any(|pattern| glob_match(pattern, path))
+        {
+            continue;
+        }
+
+        let minified = match extension(&lower) {
+            Some("html" | "htm") => minify::html(contents),
+            Some("css") => minify::css(contents),
+            Some("js" | "mjs") => minify::js(contents),
+            _ => continue,
+        };
+
+        if minified.len() < contents.len() {
+            report.minified_files += 1;
+            report.bytes_saved += contents.len() - minified.len();
+            *contents = minified.into();
+        }
+    }
+}
+
+/// Gives assets content-hashed names and points HTML and CSS references at them.
+/// Returns the fingerprinted name of each asset, keyed by its original path.
+fn fingerprint(files: &mut HashMap<String, Arc<str>>, config: &AssetConfig) -> HashMap<String, String> {
+    let is_asset = |path: &str| {
+        extension(path).is_some_and(|ext| {
+            config
+                .fingerprint_extensions
+                .iter()
+                .any(|wanted| wanted.eq_ignore_ascii_case(ext))
+        })
+    };
+    let paths_with = |files: &HashMap<String, Arc<str>>, ext: &str| -> Vec<String> {
+        files
+            .keys()
+            .filter(|path| extension(path).is_some_and(|file_ext| file_ext.eq_ignore_ascii_case(ext)))
+            .cloned()
+            .collect()
+    };
+
+    // Stylesheets are hashed after their own `url(...)` references are rewritten, so a
+    // changed image also changes the name of every stylesheet using it. References
+    // between stylesheets are left pointing at the original names.
+    let mut names: HashMap<String, String> = files
+        .iter()
+        .filter(|(path, _)| is_asset(path) && !path.to_ascii_lowercase().ends_with(".css"))
+        .map(|(path, contents)| (path.clone(), hashed_name(path, contents)))
+        .collect();
+
+    let stylesheets = paths_with(files, "css");
+    for path in &stylesheets {
+        let rewritten = rewrite_refs(&files[path], path, &names, &["url("]);
+        files.insert(path.clone(), rewritten.into());
+    }
+    for path in stylesheets.into_iter().filter(|path| is_asset(path)) {
+        let name = hashed_name(&path, &files[&path]);
+        names.insert(path, name);
+    }
+
+    for path in paths_with(files, "html") {
+        let rewritten = rewrite_refs(&files[&path], &path, &names, &["src=", "href=", "url("]);
+        files.insert(path, rewritten.into());
+    }
+
+    names
+}
+
+fn extension(path: &str) -> Option<&str> {
+    let file_name = path.rsplit('/').next()?;
+    file_name.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+/// `/css/app.css` becomes `/css/app.3f9a1c2b.css`.
+fn hashed_name(path: &str, contents: &str) -> String {
+    let hash: String = Sha256::digest(contents.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    match path.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}.{}.{}", stem, &hash[..HASH_LEN], ext),
+        None => format!("{}.{}", path, &hash[..HASH_LEN]),
+    }
+}
+
+/// Replaces every reference following one of `markers` (lowercase, matched without
+/// regard to case) that resolves to a fingerprinted file.
+fn rewrite_refs(content: &str, from: &str, names: &HashMap<String, String>, markers: &[&str]) -> String {
+    let lower = content.to_ascii_lowercase();
+    let mut out = String::with_capacity(content.len());
+    let mut pos = 0;
+
+    while let Some((start, marker)) = markers
+        .iter()
+        .filter_map(|marker| lower[pos..].find(marker).map(|i| (pos + i, *marker)))
+        .min_by_key(|(start, _)| *start)
+    {
+        let after = start + marker.len();
+        let Some((value_start, value_end)) = reference_span(content, after) else {
+            out.push_str(&content[pos..after]);
+            pos = after;
+            continue;
+        };
+
+        let value = &content[value_start..value_end];
+        out.push_str(&content[pos..value_start]);
+        match rewrite_ref(value, from, names) {
+            Some(rewritten) => out.push_str(&rewritten),
+            None => out.push_str(value),
+        }
+        pos = value_end;
+    }
+
+    out.push_str(&content[pos..]);
+    out
+}
+
+/// Byte range of a quoted or bare value starting at `at`, after optional whitespace.
+fn reference_span(content: &str, at: usize) -> Option<(usize, usize)> {
+    let rest = &content[at..];
+    let start = at + (rest.len() - rest.trim_start().len());
+    let rest = &content[start..];
+
+    let (start, len) = match rest.chars().next()? {
+        quote @ ('"' | '\'') => (start + 1, rest[1..].find(quote)?),
+        _ => (
+            start,
+            rest.find(|c: char| c.is_whitespace() || matches!(c, '>' | ')' | '"' | '\''))
+                .unwrap_or(rest.len()),
+        ),
+    };
+
+    (len > 0).then_some((start, start + len))
+}
+
+/// The reference with its file name swapped for the fingerprinted one, keeping it
+/// relative if it was and preserving any query or fragment.
+fn rewrite_ref(value: &str, from: &str, names: &HashMap<String, String>) -> Option<String> {
+    let (path, suffix) = value.split_at(value.find(['?', '#']).unwrap_or(value.len()));
+    let hashed = names.get(&resolve(from, path)?)?;
+    let file_name = hashed.rsplit('/').next()?;
+    let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+
+    Some(format!("{}{}{}", dir, file_name, suffix))
+}
+
+/// The cache key a reference in the file at `from` points at, or `None` for external
+/// and `data:` URLs.
+fn resolve(from: &str, reference: &str) -> Option<String> {
+    if reference.is_empty() || reference.starts_with("//") || reference.contains(':') {
+        return None;
+    }
+
+    let joined = match reference.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("{}{}", &from[..from.rfind('/').map_or(0, |i| i + 1)], reference),
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(format!("/{}", segments.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::plugin_static_files::PluginStaticFile;
+    use crate::server::Server;
+    use crate::structs::config::ServerConfig;
+    use crate::structs::file_cache::FileCache;
+    use crate::test_support::{RequestBuilder, send};
+
+    fn files(entries: &[(&str, &str)]) -> HashMap<String, Arc<str>> {
+        entries
+            .iter()
+            .map(|(path, contents)| (path.to_string(), Arc::from(*contents)))
+            .collect()
+    }
+
+    #[test]
+    fn html_and_css_references_point_at_hashed_names() {
+        let entries = [
+            ("/index.html", "<link href=\"/css/app.css\"><script src='js/app.js?v=1'></script><a href=\"/about.html\">"),
+            ("/css/app.css", "body { background: url(../img/bg.svg); }"),
+            ("/js/app.js", "console.log(1);"),
+            ("/img/bg.svg", "<svg/>"),
+            ("/about.html", ""),
+        ];
+        let mut files = files(&entries);
+        let names = process(&mut files, &AssetConfig::default()).fingerprints;
+
+        let svg = names["/img/bg.svg"].trim_start_matches("/img/");
+        assert!(files["/css/app.css"].contains(&format!("url(../img/{})", svg)));
+
+        let css = &names["/css/app.css"];
+        let js = names["/js/app.js"].trim_start_matches('/');
+        let html = &files["/index.html"];
+        assert!(html.contains(&format!("href=\"{}\"", css)));
+        assert!(html.contains(&format!("src='{}?v=1'", js)));
+        assert!(html.contains("href=\"/about.html\""));
+        assert!(!names.contains_key("/about.html"));
+
+        // The query string stays on the rewritten reference, so it must still resolve
+        let server = Server::new(ServerConfig::default(), Vec::new(), vec![PluginStaticFile.into()]);
+        server.cache().install(FileCache::with_assets(&entries, &AssetConfig::default()));
+        send(&server, RequestBuilder::get(&format!("/{}?v=1", js)))
+            .assert_status(200)
+            .assert_body_contains("console.log(1);");
+    }
+
+    #[test]
+    fn stylesheet_name_changes_with_referenced_image() {
+        let css = "a { background: url('/bg.svg') }";
+        let first = process(&mut files(&[("/app.css", css), ("/bg.svg", "<svg/>")]), &AssetConfig::default()).fingerprints;
+        let second =
+            process(&mut files(&[("/app.css", css), ("/bg.svg", "<svg></svg>")]), &AssetConfig::default()).fingerprints;
+
+        assert_ne!(first["/app.css"], second["/app.css"]);
+        assert!(first["/app.css"].starts_with("/app.") && first["/app.css"].ends_with(".css"));
+    }
+
+    #[test]
+    fn minifies_before_fingerprinting_and_honours_opt_outs() {
+        let mut files = files(&[
+            ("/app.css", "body {\n  color: red;\n}\n"),
+            ("/vendor/lib.js", "keep  (  this  );\n"),
+            ("/lib.min.js", "also  kept;\n"),
+        ]);
+        let config = AssetConfig {
+            minify: true,
+            minify_exclude: vec!["/vendor/**".to_string()],
+            ..AssetConfig::default()
+        };
+        let report = process(&mut files, &config);
+
+        assert_eq!(&*files["/app.css"], "body{color: red}");
+        assert_eq!(report.fingerprints["/app.css"], hashed_name("/app.css", "body{color: red}"));
+        assert_eq!(&*files["/vendor/lib.js"], "keep  (  this  );\n");
+        assert_eq!(&*files["/lib.min.js"], "also  kept;\n");
+        assert_eq!(report.minified_files, 1);
+        assert_eq!(report.bytes_saved, 7);
+    }
+
+    #[test]
+    fn external_references_are_left_alone() {
+        assert_eq!(resolve("/index.html", "https://cdn.example/app.css"), None);
+        assert_eq!(resolve("/index.html", "//cdn.example/app.css"), None);
+        assert_eq!(resolve("/index.html", "data:image/svg+xml,x"), None);
+        assert_eq!(resolve("/docs/a/page.html", "../../app.css"), Some("/app.css".to_string()));
+    }
+}
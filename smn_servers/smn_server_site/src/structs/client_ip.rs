@@ -0,0 +1,150 @@
+use crate::structs::core::Request;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An address block such as `10.0.0.0/8` or `fd00::/8`. A bare address parses as a
+/// block of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // `::ffff:10.0.0.1` from a dual-stack listener is the IPv4 peer 10.0.0.1
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_eq(u32::from(network).into(), u32::from(ip).into(), self.prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_eq(u128::from(network), u128::from(ip), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+
+        let network: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("bad address in '{}'", value))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&prefix| prefix <= max)
+                .ok_or(format!("bad prefix length in '{}'", value))?,
+            None => max,
+        };
+
+        Ok(Self {
+            network: network.to_canonical(),
+            prefix,
+        })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+fn prefix_eq(a: u128, b: u128, prefix: u8, bits: u32) -> bool {
+    let ignored = bits - u32::from(prefix);
+    ignored >= bits || (a >> ignored) == (b >> ignored)
+}
+
+/// Whether `ip` falls in one of the trusted proxy blocks.
+pub fn is_trusted(ip: IpAddr, trusted_proxies: &[Cidr]) -> bool {
+    trusted_proxies.iter().any(|block| block.contains(ip))
+}
+
+/// Resolves the originating client address for a request.
+///
+/// Forwarding headers are only believed when the peer is a trusted proxy. The
+/// `X-Forwarded-For` chain is walked right to left, skipping further trusted
+/// hops, so a client cannot spoof its address by prepending entries.
+pub fn resolve_client_ip(peer: IpAddr, req: &Request, trusted_proxies: &[Cidr]) -> IpAddr {
+    if !is_trusted(peer, trusted_proxies) {
+        return peer;
+    }
+
+    if let Some(chain) = req.header("X-Forwarded-For") {
+        for hop in chain.rsplit(',') {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) if is_trusted(ip, trusted_proxies) => continue,
+                Ok(ip) => return ip,
+                Err(_) => break,
+            }
+        }
+    }
+
+    req.header("X-Real-IP")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// `https` when a trusted proxy says so through `X-Forwarded-Proto`; this server
+/// itself only speaks plain HTTP.
+pub fn resolve_scheme(peer: IpAddr, req: &Request, trusted_proxies: &[Cidr]) -> &'static str {
+    let forwarded = req
+        .header("X-Forwarded-Proto")
+        .filter(|_| is_trusted(peer, trusted_proxies))
+        .and_then(|value| value.split(',').next());
+
+    match forwarded {
+        Some(proto) if proto.trim().eq_ignore_ascii_case("https") => "https",
+        _ => "http",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &str) -> Request {
+        Request::new(format!("GET / HTTP/1.1\r\n{}\r\n", headers))
+    }
+
+    fn proxies() -> Vec<Cidr> {
+        ["10.0.0.0/8", "::1"].map(|block| block.parse().unwrap()).to_vec()
+    }
+
+    #[test]
+    fn cidr_blocks_match_by_prefix() {
+        let block: Cidr = "192.168.4.0/22".parse().unwrap();
+
+        assert!(block.contains("192.168.7.255".parse().unwrap()));
+        assert!(!block.contains("192.168.8.0".parse().unwrap()));
+        assert!(block.contains("::ffff:192.168.5.1".parse().unwrap()));
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains("203.0.113.9".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn forwarded_headers_need_a_trusted_peer() {
+        let req = request("X-Forwarded-For: 198.51.100.7, 10.1.2.3\r\nX-Forwarded-Proto: https\r\n");
+
+        let trusted = "10.9.9.9".parse().unwrap();
+        assert_eq!(resolve_client_ip(trusted, &req, &proxies()), "198.51.100.7".parse::<IpAddr>().unwrap());
+        assert_eq!(resolve_scheme(trusted, &req, &proxies()), "https");
+
+        let untrusted = "203.0.113.1".parse().unwrap();
+        assert_eq!(resolve_client_ip(untrusted, &req, &proxies()), untrusted);
+        assert_eq!(resolve_scheme(untrusted, &req, &proxies()), "http");
+    }
+}
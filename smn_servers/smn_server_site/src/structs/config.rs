@@ -0,0 +1,45 @@
+use crate::structs::assets::AssetConfig;
+use crate::structs::client_ip::Cidr;
+use crate::structs::context::PluginSettings;
+use crate::structs::listener::ListenAddr;
+use crate::structs::metrics::MetricsConfig;
+use crate::structs::multipart::MultipartLimits;
+use crate::structs::rate_limit::RateLimitConfig;
+use std::net::SocketAddr;
+
+pub struct ServerConfig {
+    /// Every address is served at once, e.g. loopback for nginx plus a socket file.
+    pub listen: Vec<ListenAddr>,
+    /// Canonical public URL, without a trailing slash, for absolute links such as sitemaps.
+    pub base_url: String,
+    pub file_root: String,
+    /// Disables caching headers and live-reloads browsers when `file_root` changes.
+    pub dev_mode: bool,
+    /// Build steps applied to `file_root` as it is cached.
+    pub assets: AssetConfig,
+    pub metrics: MetricsConfig,
+    /// Peer address blocks whose `X-Forwarded-For` / `X-Real-IP` / `X-Forwarded-Proto`
+    /// headers are believed.
+    pub trusted_proxies: Vec<Cidr>,
+    pub rate_limit: RateLimitConfig,
+    pub multipart: MultipartLimits,
+    pub plugin_settings: PluginSettings,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: vec![ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 33030)))],
+            base_url: "http://localhost:33030".to_string(),
+            file_root: "./static".to_string(),
+            dev_mode: false,
+            assets: AssetConfig::default(),
+            metrics: MetricsConfig::default(),
+            // nginx proxies from the same host
+            trusted_proxies: ["127.0.0.0/8", "::1/128"].map(|block| block.parse().unwrap()).to_vec(),
+            rate_limit: RateLimitConfig::default(),
+            multipart: MultipartLimits::default(),
+            plugin_settings: PluginSettings::default(),
+        }
+    }
+}
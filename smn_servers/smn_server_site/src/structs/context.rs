@@ -0,0 +1,118 @@
+use crate::structs::config::ServerConfig;
+use crate::structs::file_cache::{CacheHandle, FileCache};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Typed settings for each plugin, keyed by plugin name.
+#[derive(Default)]
+pub struct PluginSettings {
+    entries: HashMap<String, Box<dyn Any + Send + Sync>>,
+}
+
+impl PluginSettings {
+    pub fn insert<T: Any + Send + Sync>(&mut self, plugin_name: &str, settings: T) {
+        self.entries.insert(plugin_name.to_string(), Box::new(settings));
+    }
+
+    pub fn get<T: Any>(&self, plugin_name: &str) -> Option<&T> {
+        self.entries.get(plugin_name)?.downcast_ref::<T>()
+    }
+}
+
+/// Key-value store shared by every plugin for the lifetime of the server.
+#[derive(Default)]
+pub struct StateStore {
+    values: RwLock<HashMap<String, Box<dyn Any + Send + Sync>>>,
+}
+
+impl StateStore {
+    /// Returns a copy of the value, or `None` if missing or of another type.
+    pub fn get<T: Any + Clone>(&self, key: &str) -> Option<T> {
+        self.values.read().unwrap().get(key)?.downcast_ref::<T>().cloned()
+    }
+
+    pub fn set<T: Any + Send + Sync>(&self, key: &str, value: T) {
+        self.values
+            .write()
+            .unwrap()
+            .insert(key.to_string(), Box::new(value));
+    }
+
+    /// Read-modify-write under a single lock, starting from `default` if unset.
+    /// Returns `None`, without calling `f`, if the key holds another type.
+    pub fn update<T: Any + Send + Sync, R>(
+        &self,
+        key: &str,
+        default: T,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let mut values = self.values.write().unwrap();
+        let entry = values
+            .entry(key.to_string())
+            .or_insert_with(|| Box::new(default));
+
+        entry.downcast_mut::<T>().map(f)
+    }
+}
+
+/// Everything a plugin can reach besides the request itself.
+#[derive(Clone)]
+pub struct PluginContext {
+    plugin_name: String,
+    config: Arc<ServerConfig>,
+    state: Arc<StateStore>,
+    cache: CacheHandle,
+}
+
+impl PluginContext {
+    pub fn new(plugin_name: &str, config: Arc<ServerConfig>, state: Arc<StateStore>, cache: CacheHandle) -> Self {
+        Self {
+            plugin_name: plugin_name.to_string(),
+            config,
+            state,
+            cache,
+        }
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// Snapshot of the file cache; a reload swaps in a new one for later requests.
+    pub fn cache(&self) -> Option<Arc<FileCache>> {
+        self.cache.current()
+    }
+
+    /// The settings registered for this plugin in `ServerConfig::plugin_settings`.
+    pub fn settings<T: Any>(&self) -> Option<&T> {
+        self.config.plugin_settings.get::<T>(&self.plugin_name)
+    }
+
+    pub fn state(&self) -> &StateStore {
+        &self.state
+    }
+
+    pub fn log(&self, message: &str) {
+        println!("[{}] {}", self.plugin_name, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_refuses_a_value_of_another_type() {
+        let state = StateStore::default();
+
+        let increment = |count: &mut u64| {
+            *count += 1;
+            *count
+        };
+
+        assert_eq!(state.update("count", 0_u64, increment), Some(1));
+        assert_eq!(state.update("count", String::new(), |text| text.len()), None);
+        assert_eq!(state.get::<u64>("count"), Some(1));
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Every attribute value is part of the builder's API, whichever the bundled code picks.
+#[allow(dead_code)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Sent cross-site too; browsers then require `Secure`, which is added automatically.
+    None,
+}
+
+/// A `Set-Cookie` header value under construction.
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Without one the cookie lasts until the browser session ends; zero deletes it.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub fn to_header(&self) -> String {
+        let mut header = format!("{}={}", self.name, self.value);
+
+        if let Some(path) = &self.path {
+            header.push_str(&format!("; Path={}", path));
+        }
+        if let Some(max_age) = self.max_age {
+            header.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            header.push_str("; Secure");
+        }
+        if self.http_only {
+            header.push_str("; HttpOnly");
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => header.push_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => header.push_str("; SameSite=Lax"),
+            Some(SameSite::None) => header.push_str("; SameSite=None"),
+            None => {}
+        }
+
+        header
+    }
+}
+
+/// `name=value` pairs from a `Cookie` request header, with any quotes around values removed.
+pub fn parse_cookie_header(value: &str) -> impl Iterator<Item = (&str, &str)> {
+    value.split(';').filter_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        Some((name.trim(), value))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pairs_and_quoted_values() {
+        let pairs: Vec<_> = parse_cookie_header("a=1; theme=\"dark\";flag; b = two ").collect();
+
+        assert_eq!(pairs, vec![("a", "1"), ("theme", "dark"), ("b", "two")]);
+    }
+
+    #[test]
+    fn builds_set_cookie_attributes() {
+        let cookie = Cookie::new("sid", "abc")
+            .path("/")
+            .max_age(Duration::from_secs(3600))
+            .http_only(true)
+            .same_site(SameSite::Lax);
+        assert_eq!(cookie.to_header(), "sid=abc; Path=/; Max-Age=3600; HttpOnly; SameSite=Lax");
+
+        let cross_site = Cookie::new("x", "1").same_site(SameSite::None);
+        assert_eq!(cross_site.to_header(), "x=1; Secure; SameSite=None");
+        assert_eq!(Cookie::new("sid", "").max_age(Duration::ZERO).to_header(), "sid=; Max-Age=0");
+    }
+}
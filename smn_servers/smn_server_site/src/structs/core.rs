@@ -1,27 +1,229 @@
+use crate::structs::cookie::{Cookie, parse_cookie_header};
+use crate::structs::multipart::MultipartForm;
+use crate::structs::session::Session;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
 pub struct Request {
     pub raw: String,
     pub path: String,
+    /// `HEAD` arrives here as `GET`, with `head_only` set.
     pub method: String,
+    /// The server drops the response body, keeping its `Content-Length`.
+    pub head_only: bool,
+    pub headers: Vec<(String, String)>,
+    /// The socket the request arrived on, when there is one.
+    pub peer: Option<SocketAddr>,
+    /// The originating client, read from forwarding headers when `peer` is a trusted proxy.
+    pub client_ip: IpAddr,
+    /// `http`, or `https` when a trusted proxy terminated TLS.
+    pub scheme: &'static str,
+    /// Filled in by the server from `Content-Length` once the request is admitted.
+    pub body: Vec<u8>,
+    /// Set instead of `body` for `multipart/form-data` requests.
+    pub multipart: Option<MultipartForm>,
+    /// Attached by `MiddlewareSession` when it is registered.
+    pub session: Option<Session>,
 }
 
 impl Request {
     pub fn new(raw: String) -> Self {
         let lines: Vec<&str> = raw.lines().collect();
 
-        let request_line = lines.get(0).unwrap_or(&"");
+        let request_line = lines.first().unwrap_or(&"");
         let parts: Vec<&str> = request_line.split_whitespace().collect();
-        
-        let method = parts.get(0).unwrap_or(&"").to_string();
+
+        let method = parts.first().unwrap_or(&"").to_string();
         let path = parts.get(1).unwrap_or(&"").to_string();
 
+        // Header lines run until the first blank line
+        let headers = lines
+            .iter()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
         Request {
             raw,
             path,
             method,
+            head_only: false,
+            headers,
+            peer: None,
+            client_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            scheme: "http",
+            body: Vec::new(),
+            multipart: None,
+            session: None,
         }
     }
+
+    /// The path without its query string or fragment.
+    pub fn path_only(&self) -> &str {
+        self.path.split(['?', '#']).next().unwrap_or("")
+    }
+
+    /// The query string without the `?` or any fragment; empty when there is none.
+    pub fn query(&self) -> &str {
+        let query = self.path.split_once('?').map_or("", |(_, query)| query);
+        query.split('#').next().unwrap_or("")
+    }
+
+    /// A copy of the request under another method, e.g. to ask which plugin would serve
+    /// it as a `GET`. Uploaded files are not copied.
+    pub fn with_method(&self, method: &str) -> Request {
+        Request {
+            raw: self.raw.clone(),
+            path: self.path.clone(),
+            method: method.to_string(),
+            head_only: false,
+            headers: self.headers.clone(),
+            peer: self.peer,
+            client_ip: self.client_ip,
+            scheme: self.scheme,
+            body: self.body.clone(),
+            multipart: None,
+            session: self.session.clone(),
+        }
+    }
+
+    /// Case-insensitive header lookup, returning the first match.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The method as the client sent it, e.g. for logs and metrics.
+    pub fn wire_method(&self) -> &str {
+        if self.head_only { "HEAD" } else { &self.method }
+    }
+
+    /// A cookie sent by the client; the first wins if the name repeats.
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("Cookie"))
+            .flat_map(|(_, value)| parse_cookie_header(value))
+            .find(|(cookie, _)| *cookie == name)
+            .map(|(_, value)| value)
+    }
+
+    /// True if a comma-separated header contains the given token (case-insensitive).
+    pub fn header_has_token(&self, name: &str, token: &str) -> bool {
+        self.header(name)
+            .map(|value| {
+                value
+                    .split(',')
+                    .any(|part| part.trim().eq_ignore_ascii_case(token))
+            })
+            .unwrap_or(false)
+    }
 }
 
 pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
     pub body: String,
-}
\ No newline at end of file
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// `200 OK` with an HTML body.
+    pub fn html(body: impl Into<String>) -> Self {
+        Response::new(200)
+            .with_header("Content-Type", "text/html; charset=utf-8")
+            .with_body(body)
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.set_header(name, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Replaces any existing header of the same name (case-insensitive).
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.remove_header(name);
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Adds a `Set-Cookie` header alongside any already present.
+    pub fn add_cookie(&mut self, cookie: &Cookie) {
+        self.headers.push(("Set-Cookie".to_string(), cookie.to_header()));
+    }
+
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_html(&self) -> bool {
+        self.header("Content-Type")
+            .map(|value| value.starts_with("text/html"))
+            .unwrap_or(false)
+    }
+
+    /// Serializes the status line, headers and body for the wire.
+    pub fn to_http(&self) -> String {
+        let mut out = format!("HTTP/1.1 {} {}\r\n", self.status, status_reason(self.status));
+
+        for (name, value) in &self.headers {
+            out.push_str(&format!("{}: {}\r\n", name, value));
+        }
+
+        if self.header("Content-Length").is_none() {
+            out.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+
+        out.push_str("\r\n");
+        out.push_str(&self.body);
+        out
+    }
+}
+
+fn status_reason(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,3 @@
+//! The static tree compiled in by `build.rs` when the `embed` feature is on.
+
+include!(concat!(env!("OUT_DIR"), "/embedded_static.rs"));
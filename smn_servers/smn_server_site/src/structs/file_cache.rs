@@ -1,27 +1,125 @@
+use crate::structs::assets::{self, AssetConfig};
+#[cfg(feature = "embed")]
+use crate::structs::embedded;
+#[cfg(feature = "embed")]
+use std::time::Duration;
 use std::{
     collections::HashMap,
     fs,
-    sync::OnceLock,
+    sync::{Arc, RwLock, atomic::{AtomicU64, Ordering}},
     path::Path,
+    time::SystemTime,
 };
 
-static FILE_CACHE: OnceLock<FileCache> = OnceLock::new();
+static GENERATION: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug)]
 pub struct FileCache {
-    files: HashMap<String, String>,
+    root: String,
+    assets: AssetConfig,
+    /// Bumped on every load so derived data can tell when it is stale.
+    generation: u64,
+    files: HashMap<String, Arc<str>>,
+    modified: HashMap<String, SystemTime>,
+    /// Content-hashed name to the original path it serves.
+    aliases: HashMap<String, String>,
+}
+
+/// The loaded `FileCache`, shared by the server and every plugin context. Clones
+/// see the same cache, so a reload reaches all of them.
+#[derive(Clone, Default)]
+pub struct CacheHandle {
+    current: Arc<RwLock<Option<Arc<FileCache>>>>,
+}
+
+impl CacheHandle {
+    pub fn init(&self, root: &str, assets: &AssetConfig) {
+        self.install(FileCache::load(root, assets));
+    }
+
+    /// Re-reads the root the cache was initialized with and swaps it in.
+    pub fn reload(&self) {
+        if let Some(current) = self.current() {
+            self.install(FileCache::load(&current.root, &current.assets));
+        }
+    }
+
+    pub fn install(&self, cache: FileCache) {
+        *self.current.write().unwrap() = Some(Arc::new(cache));
+    }
+
+    /// Snapshot of the cache as it stands; unaffected by later reloads.
+    pub fn current(&self) -> Option<Arc<FileCache>> {
+        self.current.read().unwrap().clone()
+    }
 }
 
 impl FileCache {
-    pub fn init(root: &str) {
-        let mut map = HashMap::new();
-        Self::load_dir(Path::new(root), &mut map, "");
+    fn load(root: &str, assets: &AssetConfig) -> FileCache {
+        let mut cache = FileCache {
+            root: root.to_string(),
+            assets: assets.clone(),
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed) + 1,
+            files: HashMap::new(),
+            modified: HashMap::new(),
+            aliases: HashMap::new(),
+        };
+        #[cfg(feature = "embed")]
+        cache.load_embedded();
+        cache.load_dir(Path::new(root), "");
+        cache.process_assets();
+        cache
+    }
+
+    fn process_assets(&mut self) {
+        let report = assets::process(&mut self.files, &self.assets);
+        if self.assets.minify {
+            println!(
+                "Minified {} file(s), saved {} bytes",
+                report.minified_files, report.bytes_saved
+            );
+        }
+        self.aliases = report
+            .fingerprints
+            .into_iter()
+            .map(|(original, hashed)| (hashed, original))
+            .collect();
+    }
+
+    /// A cache holding exactly `files`, read from no directory.
+    #[cfg(test)]
+    pub fn from_files(files: &[(&str, &str)]) -> FileCache {
+        FileCache {
+            root: String::new(),
+            assets: AssetConfig::default(),
+            generation: 0,
+            files: files.iter().map(|(path, contents)| (path.to_string(), Arc::from(*contents))).collect(),
+            modified: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
 
-        FILE_CACHE.set(FileCache { files: map })
-            .expect("FileCache already initialized");
+    /// `from_files`, run through the asset pipeline as a load would.
+    #[cfg(test)]
+    pub fn with_assets(files: &[(&str, &str)], assets: &AssetConfig) -> FileCache {
+        let mut cache = Self::from_files(files);
+        cache.assets = assets.clone();
+        cache.process_assets();
+        cache
     }
 
-    fn load_dir(base: &Path, map: &mut HashMap<String, String>, prefix: &str) {
+    /// Seeds the cache with the files compiled into the binary; anything under `root`
+    /// loaded afterwards replaces them.
+    #[cfg(feature = "embed")]
+    fn load_embedded(&mut self) {
+        for (path, contents, modified) in embedded::EMBEDDED {
+            self.files.insert(path.to_string(), Arc::from(*contents));
+            self.modified
+                .insert(path.to_string(), SystemTime::UNIX_EPOCH + Duration::from_secs(*modified));
+        }
+    }
+
+    fn load_dir(&mut self, base: &Path, prefix: &str) {
         if let Ok(entries) = fs::read_dir(base) {
             for entry in entries.flatten() {
                 let path = entry.path();
@@ -30,17 +128,52 @@ impl FileCache {
                 let key = format!("{}/{}", prefix, name).replace("//", "/");
 
                 if path.is_dir() {
-                    Self::load_dir(&path, map, &key);
-                } else if path.is_file() {
-                    if let Ok(contents) = fs::read_to_string(&path) {
-                        map.insert(key, contents);
+                    self.load_dir(&path, &key);
+                } else if path.is_file()
+                    && let Ok(contents) = fs::read_to_string(&path)
+                {
+                    if let Ok(modified) = entry.metadata().and_then(|meta| meta.modified()) {
+                        self.modified.insert(key.clone(), modified);
                     }
+                    self.files.insert(key, contents.into());
                 }
             }
         }
     }
 
-    pub fn get(path: &str) -> Option<&'static str> {
-        FILE_CACHE.get()?.files.get(path).map(|v| v.as_str())
+    pub fn entry_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.files.values().map(|contents| contents.len()).sum()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Every cached path, in no particular order. Fingerprinted names are not included.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// When the file was last modified on disk.
+    pub fn modified(&self, path: &str) -> Option<SystemTime> {
+        self.modified.get(self.original(path)).copied()
+    }
+
+    /// Serves both the original path and its fingerprinted name.
+    pub fn file(&self, path: &str) -> Option<Arc<str>> {
+        self.files.get(self.original(path)).cloned()
+    }
+
+    /// Whether `path` is a content-hashed name, which never changes contents.
+    pub fn is_fingerprinted(&self, path: &str) -> bool {
+        self.aliases.contains_key(path)
+    }
+
+    fn original<'a>(&'a self, path: &'a str) -> &'a str {
+        self.aliases.get(path).map_or(path, String::as_str)
     }
 }
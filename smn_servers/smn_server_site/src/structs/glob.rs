@@ -0,0 +1,67 @@
+/// Matches a URL path against a glob: `*` and `?` stay within one segment,
+/// `**` spans any number of segments and `{a,b}` matches either alternative.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    if let Some(open) = pattern.find('{')
+        && let Some(close) = pattern[open..].find('}').map(|offset| open + offset)
+    {
+        let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+        return pattern[open + 1..close]
+            .split(',')
+            .any(|choice| glob_match(&format!("{}{}{}", head, choice, tail), path));
+    }
+
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+fn matches(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        // `**/` consumes zero or more whole segments, so `/**/a` also matches `/a`
+        [b'*', b'*', b'/', rest @ ..] => {
+            matches(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(index, &byte)| byte == b'/' && matches(rest, &path[index + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        [b'*', rest @ ..] => {
+            let segment = path.iter().position(|&byte| byte == b'/').unwrap_or(path.len());
+            (0..=segment).any(|skip| matches(rest, &path[skip..]))
+        }
+        [b'?', rest @ ..] => matches!(path.first(), Some(&byte) if byte != b'/') && matches(rest, &path[1..]),
+        [expected, rest @ ..] => path.first() == Some(expected) && matches(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_stars_stay_within_a_segment() {
+        assert!(glob_match("/assets/*.css", "/assets/site.css"));
+        assert!(!glob_match("/assets/*.css", "/assets/css/site.css"));
+        assert!(glob_match("/img/?.png", "/img/a.png"));
+        assert!(!glob_match("/img/?.png", "/img/ab.png"));
+        assert!(!glob_match("/a?b", "/a/b"));
+    }
+
+    #[test]
+    fn double_stars_span_segments() {
+        assert!(glob_match("/assets/**", "/assets/js/vendor/lib.js"));
+        assert!(glob_match("/assets/**", "/assets/"));
+        assert!(glob_match("/**/*.html", "/index.html"));
+        assert!(glob_match("/**/*.html", "/blog/2024/post.html"));
+        assert!(!glob_match("/**/*.html", "/blog/post.htm"));
+    }
+
+    #[test]
+    fn braces_match_any_alternative() {
+        assert!(glob_match("/**/*.{woff,woff2}", "/fonts/inter.woff2"));
+        assert!(glob_match("/{img,media}/**", "/media/clip.mp4"));
+        assert!(!glob_match("/{img,media}/**", "/docs/clip.mp4"));
+        // An unclosed brace is matched literally
+        assert!(glob_match("/a{b", "/a{b"));
+    }
+}
@@ -0,0 +1,46 @@
+use crate::structs::core::Response;
+use crate::structs::file_cache::FileCache;
+
+pub const HEALTH_PATH: &str = "/healthz";
+pub const READY_PATH: &str = "/readyz";
+
+/// Answers liveness and readiness probes, or `None` for any other path.
+pub fn probe_response(path: &str, cache: Option<&FileCache>) -> Option<Response> {
+    let (status, body) = match path {
+        HEALTH_PATH => (200, "ok"),
+        READY_PATH => match readiness(cache) {
+            Ok(()) => (200, "ready"),
+            Err(reason) => (503, reason),
+        },
+        _ => return None,
+    };
+
+    Some(
+        Response::new(status)
+            .with_header("Content-Type", "text/plain; charset=utf-8")
+            .with_header("Cache-Control", "no-store")
+            .with_body(body),
+    )
+}
+
+/// The site is ready once the `FileCache` holds at least one file, so a wrong
+/// `file_root` keeps it out of rotation instead of answering every page with 404.
+fn readiness(cache: Option<&FileCache>) -> Result<(), &'static str> {
+    match cache {
+        None => Err("file cache not loaded"),
+        Some(cache) if cache.entry_count() == 0 => Err("file cache is empty"),
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_only_with_files_to_serve() {
+        assert_eq!(readiness(None), Err("file cache not loaded"));
+        assert_eq!(readiness(Some(&FileCache::from_files(&[]))), Err("file cache is empty"));
+        assert_eq!(readiness(Some(&FileCache::from_files(&[("/about.html", "")]))), Ok(()));
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// UTC calendar fields for a point in time.
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// Days since the epoch, used for the weekday.
+    days: i64,
+}
+
+impl DateTime {
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs() as i64;
+        let days = secs.div_euclid(86_400);
+        let of_day = secs.rem_euclid(86_400);
+
+        // Howard Hinnant's days-to-civil conversion
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (of_day / 3_600) as u32,
+            minute: (of_day % 3_600 / 60) as u32,
+            second: (of_day % 60) as u32,
+            days,
+        }
+    }
+
+    fn weekday(&self) -> &'static str {
+        DAYS[self.days.rem_euclid(7) as usize]
+    }
+
+    fn month_name(&self) -> &'static str {
+        MONTHS[self.month as usize - 1]
+    }
+}
+
+/// IMF-fixdate as used by `Date`, `Expires` and `Last-Modified`.
+pub fn http_date(time: SystemTime) -> String {
+    let t = DateTime::from_system_time(time);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        t.weekday(),
+        t.day,
+        t.month_name(),
+        t.year,
+        t.hour,
+        t.minute,
+        t.second
+    )
+}
+
+/// RFC 3339 timestamp in UTC, e.g. `2024-05-01T12:00:00Z`.
+pub fn rfc3339(time: SystemTime) -> String {
+    let t = DateTime::from_system_time(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+/// The UTC instant for a calendar date and time, or `None` for an impossible date.
+pub fn from_civil(year: i64, month: u32, day: u32, hour: u32, minute: u32) -> Option<SystemTime> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // Inverse of the conversion in `DateTime::from_system_time`
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour as i64 * 3_600 + minute as i64 * 60;
+    let secs = u64::try_from(secs).ok()?;
+    let time = UNIX_EPOCH + Duration::from_secs(secs);
+
+    // Reject dates like February 30th that roll over into the next month
+    (DateTime::from_system_time(time).day == day).then_some(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn formats_imf_fixdate() {
+        assert_eq!(http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(http_date(at(784_111_777)), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(http_date(at(951_782_400)), "Tue, 29 Feb 2000 00:00:00 GMT");
+        assert_eq!(rfc3339(at(784_111_777)), "1994-11-06T08:49:37Z");
+    }
+
+    #[test]
+    fn civil_dates_round_trip_and_reject_impossible_days() {
+        assert_eq!(from_civil(2024, 2, 29, 23, 59), Some(at(1_709_251_140)));
+        assert_eq!(from_civil(1970, 1, 1, 0, 0), Some(UNIX_EPOCH));
+        assert_eq!(from_civil(2023, 2, 29, 0, 0), None);
+        assert_eq!(from_civil(2024, 13, 1, 0, 0), None);
+        assert_eq!(from_civil(1969, 12, 31, 0, 0), None);
+    }
+}
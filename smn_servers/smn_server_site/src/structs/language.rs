@@ -0,0 +1,85 @@
+//! Language tags for content negotiation.
+
+/// Tags from an `Accept-Language` header, lowercased and most preferred first.
+/// Entries with `q=0` and the `*` wildcard are dropped.
+pub fn accepted_languages(header: &str) -> Vec<String> {
+    let mut ranked: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim().to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+
+    // Stable, so equal qualities keep the client's order
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Whether `tag` asks for `language`: `fr-ca` matches a site language of `fr`.
+pub fn tag_matches(tag: &str, language: &str) -> bool {
+    tag.eq_ignore_ascii_case(language)
+        || tag
+            .get(..language.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(language))
+            && tag.as_bytes().get(language.len()) == Some(&b'-')
+}
+
+/// Splits `/fr/about.html` into `fr` and `/about.html` when the first segment looks
+/// like a language tag (`fr`, `pt-br`); whether the site offers it is up to the caller.
+pub fn split_language_prefix(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix('/')?;
+    let (tag, _) = rest.split_once('/')?;
+
+    let (primary, region) = tag.split_once('-').unwrap_or((tag, "AA"));
+    let looks_like_tag = (2..=3).contains(&primary.len())
+        && primary.bytes().all(|byte| byte.is_ascii_alphabetic())
+        && (2..=4).contains(&region.len())
+        && region.bytes().all(|byte| byte.is_ascii_alphanumeric());
+
+    looks_like_tag.then(|| (tag, &path[tag.len() + 1..]))
+}
+
+/// `/about.html` translated to `fr` is `/about.fr.html`.
+pub fn variant_path(path: &str, language: &str) -> String {
+    let file_start = path.rfind('/').map_or(0, |i| i + 1);
+
+    match path[file_start..].rfind('.') {
+        Some(dot) => {
+            let dot = file_start + dot;
+            format!("{}.{}{}", &path[..dot], language, &path[dot..])
+        }
+        None => format!("{}.{}", path, language),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_language_is_ranked_by_quality() {
+        assert_eq!(
+            accepted_languages("en;q=0.5, fr-CA, de;q=0, *;q=0.1, es;q=0.5"),
+            vec!["fr-ca", "en", "es"]
+        );
+        assert!(tag_matches("fr-ca", "fr"));
+        assert!(!tag_matches("fra", "fr"));
+    }
+
+    #[test]
+    fn prefixes_and_variants() {
+        assert_eq!(split_language_prefix("/fr/about.html"), Some(("fr", "/about.html")));
+        assert_eq!(split_language_prefix("/pt-br/"), Some(("pt-br", "/")));
+        assert_eq!(split_language_prefix("/blog/post.html"), None);
+        assert_eq!(split_language_prefix("/about.html"), None);
+        assert_eq!(variant_path("/docs/about.html", "fr"), "/docs/about.fr.html");
+        assert_eq!(variant_path("/v1.2/readme", "fr"), "/v1.2/readme.fr");
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+pub use smn_shared::listen::{ListenAddr, Listener};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// An accepted connection, whichever kind of listener it came from.
+pub enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    /// `None` for Unix sockets, whose peers are local processes.
+    #[cfg(not(feature = "async"))]
+    pub fn peer_addr(&self) -> Option<std::net::SocketAddr> {
+        match self {
+            Self::Tcp(stream) => stream.peer_addr().ok(),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    #[cfg(not(feature = "async"))]
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Waits for the next connection on a blocking listener.
+#[cfg(not(feature = "async"))]
+pub fn accept(listener: &Listener) -> io::Result<Connection> {
+    match listener {
+        Listener::Tcp(listener) => listener.accept().map(|(stream, _)| Connection::Tcp(stream)),
+        #[cfg(unix)]
+        Listener::Unix(listener) => listener.accept().map(|(stream, _)| Connection::Unix(stream)),
+    }
+}
+
+/// Tokio listeners for the async backend.
+#[cfg(feature = "async")]
+pub enum AsyncListener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+#[cfg(feature = "async")]
+impl AsyncListener {
+    /// Must be called from within the runtime.
+    pub fn from_std(listener: Listener) -> io::Result<Self> {
+        match listener {
+            Listener::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::TcpListener::from_std(listener).map(Self::Tcp)
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::UnixListener::from_std(listener).map(Self::Unix)
+            }
+        }
+    }
+}
+
+/// A tokio stream that can be handed to the blocking takeover handlers.
+#[cfg(feature = "async")]
+pub trait IntoConnection {
+    fn into_connection(self) -> io::Result<Connection>;
+}
+
+#[cfg(feature = "async")]
+impl IntoConnection for tokio::net::TcpStream {
+    fn into_connection(self) -> io::Result<Connection> {
+        let stream = self.into_std()?;
+        stream.set_nonblocking(false)?;
+        Ok(Connection::Tcp(stream))
+    }
+}
+
+#[cfg(all(feature = "async", unix))]
+impl IntoConnection for tokio::net::UnixStream {
+    fn into_connection(self) -> io::Result<Connection> {
+        let stream = self.into_std()?;
+        stream.set_nonblocking(false)?;
+        Ok(Connection::Unix(stream))
+    }
+}
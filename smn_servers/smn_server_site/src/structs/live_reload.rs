@@ -0,0 +1,219 @@
+use crate::structs::core::Response;
+use crate::structs::file_cache::CacheHandle;
+use crate::structs::listener::Connection;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Server-Sent Events endpoint the injected script listens on.
+pub const LIVE_RELOAD_PATH: &str = "/__livereload";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls between keep-alive comments, which also prune dead clients.
+const KEEPALIVE_POLLS: u32 = 30;
+
+const RELOAD_SCRIPT: &str = "<script>\
+new EventSource(\"/__livereload\").addEventListener(\"reload\", () => location.reload());\
+</script>";
+
+/// Development-mode file watcher that tells connected browsers to reload.
+pub struct LiveReload {
+    clients: Mutex<Vec<Connection>>,
+}
+
+impl LiveReload {
+    /// Starts watching `root` on a background thread, reloading `cache` on changes.
+    pub fn start(root: &str, cache: CacheHandle) -> Arc<Self> {
+        let live_reload = Arc::new(Self {
+            clients: Mutex::new(Vec::new()),
+        });
+
+        let watcher = Arc::clone(&live_reload);
+        let root = PathBuf::from(root);
+
+        thread::spawn(move || {
+            let mut last = scan(&root);
+            let mut polls = 0;
+
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                polls += 1;
+
+                let current = scan(&root);
+                if current != last {
+                    last = current;
+                    println!("Change detected under {}, reloading...", root.display());
+
+                    cache.reload();
+                    watcher.broadcast(&format!("event: reload\ndata: {:x}\n\n", current));
+                } else if polls % KEEPALIVE_POLLS == 0 {
+                    watcher.broadcast(": keepalive\n\n");
+                }
+            }
+        });
+
+        live_reload
+    }
+
+    /// Takes over the connection as an event stream; it stays open until the client leaves.
+    pub fn subscribe(&self, mut stream: Connection) {
+        let head = "HTTP/1.1 200 OK\r\n\
+Content-Type: text/event-stream\r\n\
+Cache-Control: no-store\r\n\
+Connection: keep-alive\r\n\
+X-Accel-Buffering: no\r\n\
+\r\n\
+retry: 1000\n\n";
+
+        // A stalled browser must not hold up the broadcast to everyone else
+        let _ = stream.set_write_timeout(Some(Duration::from_secs(2)));
+
+        if stream.write_all(head.as_bytes()).is_ok() {
+            self.clients.lock().unwrap().push(stream);
+        }
+    }
+
+    /// Strips caching headers and injects the reload script into HTML pages.
+    pub fn decorate(&self, resp: &mut Response) {
+        resp.remove_header("ETag");
+        resp.remove_header("Last-Modified");
+        resp.remove_header("Expires");
+        resp.set_header("Cache-Control", "no-store");
+
+        if !resp.is_html() {
+            return;
+        }
+
+        // The inline reload script would be blocked, so only report violations in dev
+        if let Some(csp) = resp.header("Content-Security-Policy").map(str::to_string) {
+            resp.remove_header("Content-Security-Policy");
+            resp.set_header("Content-Security-Policy-Report-Only", &csp);
+        }
+
+        match resp.body.rfind("</body>") {
+            Some(index) => resp.body.insert_str(index, RELOAD_SCRIPT),
+            None => resp.body.push_str(RELOAD_SCRIPT),
+        }
+    }
+
+    fn broadcast(&self, event: &str) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|stream| stream.write_all(event.as_bytes()).is_ok());
+    }
+}
+
+/// Fingerprint of every path, size and modification time under `root`.
+fn scan(root: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    scan_dir(root, &mut hasher);
+    hasher.finish()
+}
+
+fn scan_dir(dir: &Path, hasher: &mut DefaultHasher) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        path.hash(hasher);
+
+        if path.is_dir() {
+            scan_dir(&path, hasher);
+        } else if let Ok(meta) = fs::metadata(&path) {
+            meta.len().hash(hasher);
+            meta.modified().ok().hash(hasher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ScratchDir;
+
+    fn live_reload() -> LiveReload {
+        LiveReload {
+            clients: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn decorate_injects_the_script_and_disables_caching() {
+        let mut page = Response::new(200)
+            .with_header("Content-Type", "text/html; charset=utf-8")
+            .with_header("ETag", "\"abc\"")
+            .with_header("Cache-Control", "max-age=600")
+            .with_header("Content-Security-Policy", "default-src 'self'")
+            .with_body("<html><body><p>hi</p></body></html>");
+        live_reload().decorate(&mut page);
+
+        assert_eq!(page.body, format!("<html><body><p>hi</p>{}</body></html>", RELOAD_SCRIPT));
+        assert_eq!(page.header("ETag"), None);
+        assert_eq!(page.header("Cache-Control"), Some("no-store"));
+        assert_eq!(page.header("Content-Security-Policy"), None);
+        assert_eq!(page.header("Content-Security-Policy-Report-Only"), Some("default-src 'self'"));
+
+        let mut fragment = Response::new(200)
+            .with_header("Content-Type", "text/html")
+            .with_body("<p>partial</p>");
+        live_reload().decorate(&mut fragment);
+        assert!(fragment.body.ends_with(RELOAD_SCRIPT));
+
+        let mut style = Response::new(200).with_header("Content-Type", "text/css").with_body("p{}");
+        live_reload().decorate(&mut style);
+        assert_eq!(style.body, "p{}");
+        assert_eq!(style.header("Cache-Control"), Some("no-store"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broadcast_reaches_subscribers_and_drops_departed_ones() {
+        use std::io::Read;
+        use std::os::unix::net::UnixStream;
+
+        let live_reload = live_reload();
+        let (server, mut browser) = UnixStream::pair().unwrap();
+        let (gone, departed) = UnixStream::pair().unwrap();
+        live_reload.subscribe(Connection::Unix(server));
+        live_reload.subscribe(Connection::Unix(gone));
+        drop(departed);
+
+        live_reload.broadcast("event: reload\ndata: 1\n\n");
+        assert_eq!(live_reload.clients.lock().unwrap().len(), 1);
+
+        drop(live_reload);
+        let mut received = String::new();
+        browser.read_to_string(&mut received).unwrap();
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n"));
+        assert!(received.ends_with("retry: 1000\n\nevent: reload\ndata: 1\n\n"));
+    }
+
+    #[test]
+    fn scan_notices_added_and_changed_files() {
+        let dir = ScratchDir::new("live-reload");
+        let root = dir.path();
+        fs::create_dir_all(root.join("blog")).unwrap();
+
+        fs::write(root.join("index.html"), "one").unwrap();
+        let before = scan(root);
+        assert_eq!(scan(root), before);
+
+        fs::write(root.join("index.html"), "two!").unwrap();
+        let changed = scan(root);
+        assert_ne!(changed, before);
+
+        fs::write(root.join("blog/post.md"), "").unwrap();
+        assert_ne!(scan(root), changed);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::structs::file_cache::FileCache;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Methods reported as-is; anything else is folded into `OTHER`.
+const KNOWN_METHODS: [&str; 9] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "PATCH", "OPTIONS", "CONNECT", "TRACE",
+];
+
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub path: String,
+    /// Addresses allowed to scrape besides loopback.
+    pub allowlist: Vec<IpAddr>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "/metrics".to_string(),
+            allowlist: Vec::new(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Loopback and allowlisted clients may scrape. `client` is the resolved
+    /// address, so requests forwarded by nginx are judged by their origin.
+    pub fn allows(&self, client: IpAddr) -> bool {
+        client.is_loopback() || self.allowlist.contains(&client)
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Request, latency and traffic counters exposed in Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<(String, u16, String), u64>>,
+    latency: Mutex<BTreeMap<String, Histogram>>,
+    in_flight: AtomicI64,
+    bytes_sent: AtomicU64,
+}
+
+/// Counts a connection as in flight until dropped.
+pub struct ConnectionGuard {
+    metrics: Arc<Metrics>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub fn track_connection(self: &Arc<Self>) -> ConnectionGuard {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard {
+            metrics: Arc::clone(self),
+        }
+    }
+
+    pub fn record(&self, plugin: &str, method: &str, status: u16, elapsed: Duration, bytes: usize) {
+        let method = if KNOWN_METHODS.contains(&method) {
+            method
+        } else {
+            "OTHER"
+        };
+
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((plugin.to_string(), status, method.to_string()))
+            .or_default() += 1;
+
+        let seconds = elapsed.as_secs_f64();
+        let mut latency = self.latency.lock().unwrap();
+        let histogram = latency.entry(plugin.to_string()).or_default();
+
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn render(&self, cache: Option<&FileCache>) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP smn_site_requests_total Requests served, by plugin, status and method.\n");
+        out.push_str("# TYPE smn_site_requests_total counter\n");
+        for ((plugin, status, method), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "smn_site_requests_total{{plugin=\"{}\",status=\"{}\",method=\"{}\"}} {}",
+                escape_label(plugin),
+                status,
+                method,
+                count
+            );
+        }
+
+        out.push_str("# HELP smn_site_request_duration_seconds Time from request read to response written.\n");
+        out.push_str("# TYPE smn_site_request_duration_seconds histogram\n");
+        for (plugin, histogram) in self.latency.lock().unwrap().iter() {
+            let plugin = escape_label(plugin);
+
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(
+                    out,
+                    "smn_site_request_duration_seconds_bucket{{plugin=\"{}\",le=\"{}\"}} {}",
+                    plugin, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "smn_site_request_duration_seconds_bucket{{plugin=\"{}\",le=\"+Inf\"}} {}",
+                plugin, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "smn_site_request_duration_seconds_sum{{plugin=\"{}\"}} {}",
+                plugin, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "smn_site_request_duration_seconds_count{{plugin=\"{}\"}} {}",
+                plugin, histogram.count
+            );
+        }
+
+        out.push_str("# HELP smn_site_connections_in_flight Connections currently being handled.\n");
+        out.push_str("# TYPE smn_site_connections_in_flight gauge\n");
+        let _ = writeln!(
+            out,
+            "smn_site_connections_in_flight {}",
+            self.in_flight.load(Ordering::Relaxed)
+        );
+
+        let (entries, bytes) = cache
+            .map(|cache| (cache.entry_count(), cache.total_bytes()))
+            .unwrap_or((0, 0));
+
+        out.push_str("# HELP smn_site_file_cache_entries Files held in the FileCache.\n");
+        out.push_str("# TYPE smn_site_file_cache_entries gauge\n");
+        let _ = writeln!(out, "smn_site_file_cache_entries {}", entries);
+
+        out.push_str("# HELP smn_site_file_cache_bytes Total size of files held in the FileCache.\n");
+        out.push_str("# TYPE smn_site_file_cache_bytes gauge\n");
+        let _ = writeln!(out, "smn_site_file_cache_bytes {}", bytes);
+
+        out.push_str("# HELP smn_site_response_bytes_total Bytes written in responses.\n");
+        out.push_str("# TYPE smn_site_response_bytes_total counter\n");
+        let _ = writeln!(
+            out,
+            "smn_site_response_bytes_total {}",
+            self.bytes_sent.load(Ordering::Relaxed)
+        );
+
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value on the sample line starting with `series`.
+    fn sample(out: &str, series: &str) -> f64 {
+        out.lines()
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("no sample for {}:\n{}", series, out))
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn histogram_buckets_accumulate_up_to_inf() {
+        let metrics = Metrics::default();
+        metrics.record("Static", "GET", 200, Duration::from_millis(3), 10);
+        metrics.record("Static", "GET", 200, Duration::from_millis(30), 20);
+        metrics.record("Static", "HEAD", 404, Duration::from_secs(9), 5);
+
+        let cache = FileCache::from_files(&[("/a.html", "abc"), ("/b.css", "")]);
+        let out = metrics.render(Some(&cache));
+
+        let bucket = |le: &str| {
+            sample(&out, &format!("smn_site_request_duration_seconds_bucket{{plugin=\"Static\",le=\"{}\"}}", le))
+        };
+        assert_eq!(bucket("0.0025"), 0.0);
+        assert_eq!(bucket("0.005"), 1.0);
+        assert_eq!(bucket("0.025"), 1.0);
+        assert_eq!(bucket("0.05"), 2.0);
+        assert_eq!(bucket("5"), 2.0);
+        assert_eq!(bucket("+Inf"), 3.0);
+
+        let sum = sample(&out, "smn_site_request_duration_seconds_sum{plugin=\"Static\"}");
+        assert!((sum - 9.033).abs() < 1e-9, "{}", sum);
+        assert_eq!(sample(&out, "smn_site_request_duration_seconds_count{plugin=\"Static\"}"), 3.0);
+
+        assert_eq!(sample(&out, "smn_site_requests_total{plugin=\"Static\",status=\"200\",method=\"GET\"}"), 2.0);
+        assert_eq!(sample(&out, "smn_site_requests_total{plugin=\"Static\",status=\"404\",method=\"HEAD\"}"), 1.0);
+        assert_eq!(sample(&out, "smn_site_response_bytes_total"), 35.0);
+        assert_eq!(sample(&out, "smn_site_file_cache_entries"), 2.0);
+        assert_eq!(sample(&out, "smn_site_file_cache_bytes"), 3.0);
+    }
+
+    #[test]
+    fn unknown_methods_are_folded_and_labels_escaped() {
+        let metrics = Metrics::default();
+        metrics.record("Odd \"name\"\\\n", "BREW", 418, Duration::ZERO, 0);
+        metrics.record("Odd \"name\"\\\n", "PROPFIND", 418, Duration::ZERO, 0);
+
+        let out = metrics.render(None);
+
+        assert_eq!(
+            sample(&out, r#"smn_site_requests_total{plugin="Odd \"name\"\\\n",status="418",method="OTHER"}"#),
+            2.0
+        );
+        assert!(!out.contains("BREW") && !out.contains("PROPFIND"));
+        assert_eq!(sample(&out, r#"smn_site_request_duration_seconds_count{plugin="Odd \"name\"\\\n"}"#), 2.0);
+        assert_eq!(sample(&out, "smn_site_file_cache_entries"), 0.0);
+    }
+}
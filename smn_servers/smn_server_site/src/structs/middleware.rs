@@ -0,0 +1,18 @@
+use crate::structs::core::{Request, Response};
+
+/// Cross-cutting request handling that runs around plugin dispatch.
+pub trait Middleware: Send + Sync {
+    fn middleware_name(&self) -> &str;
+
+    /// Runs once the request head is parsed, before admission and any `middleware_before`
+    /// hook, to attach per-request data such as a session.
+    fn middleware_prepare(&self, _req: &mut Request) {}
+
+    /// Runs before any plugin; returning a response sends it instead.
+    fn middleware_before(&self, _req: &Request) -> Option<Response> {
+        None
+    }
+
+    /// Runs on every response before it is written, in reverse registration order.
+    fn middleware_after(&self, _req: &Request, _resp: &mut Response) {}
+}
@@ -0,0 +1,429 @@
+//! Conservative HTML, CSS and JS minifiers: comments and redundant whitespace go,
+//! everything else (strings, `<pre>`, `<textarea>`, regex literals) is copied as-is.
+
+use std::iter::Peekable;
+
+/// Elements whose contents are copied without whitespace collapsing, or minified
+/// with their own language.
+const RAW_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// `<script>` types whose contents are JavaScript; anything else, such as JSON or
+/// client-side templates, is copied as-is.
+const JS_SCRIPT_TYPES: [&str; 5] = [
+    "text/javascript",
+    "application/javascript",
+    "text/ecmascript",
+    "application/ecmascript",
+    "module",
+];
+
+/// Words after which a `/` starts a regex literal rather than a division.
+const REGEX_KEYWORDS: [&str; 14] = [
+    "return", "typeof", "case", "do", "else", "in", "of", "new", "delete", "void", "throw", "yield",
+    "await", "instanceof",
+];
+
+pub fn html(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut pos = 0;
+
+    while pos < src.len() {
+        let rest = &src[pos..];
+
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |i| i + 3);
+            // Conditional comments are markup for old IE, not commentary.
+            if rest.starts_with("<!--[if") || rest.starts_with("<!--<![endif") {
+                out.push_str(&rest[..end]);
+            }
+            pos += end;
+        } else if rest.starts_with('<') {
+            let end = tag_end(rest);
+            let tag = &rest[..end];
+            push_tag(&mut out, tag);
+            pos += end;
+
+            let name = tag_name(tag);
+            if let Some(raw) = RAW_ELEMENTS.iter().find(|raw| name.eq_ignore_ascii_case(raw)) {
+                let body = &src[pos..];
+                let close = find_ignore_case(body, &format!("</{}", raw)).unwrap_or(body.len());
+                let contents = &body[..close];
+
+                match *raw {
+                    "script" if is_javascript(tag) => out.push_str(js(contents).trim()),
+                    "style" => out.push_str(&css(contents)),
+                    _ => out.push_str(contents),
+                }
+                pos += close;
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            push_collapsed(&mut out, &rest[..end]);
+            pos += end;
+        }
+    }
+
+    out.trim().to_string()
+}
+
+pub fn css(src: &str) -> String {
+    let is_punct = |c: char| matches!(c, '{' | '}' | ';' | ',' | '>');
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut pending_space = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                pending_space = true;
+            }
+            c if c.is_whitespace() => pending_space = true,
+            c => {
+                let last = out.chars().last();
+                if pending_space && last.is_some_and(|last| !is_punct(last)) && !is_punct(c) {
+                    out.push(' ');
+                }
+                pending_space = false;
+
+                if c == '}' && last == Some(';') {
+                    out.pop();
+                }
+                out.push(c);
+
+                if c == '"' || c == '\'' {
+                    copy_string(&mut out, &mut chars, c);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+pub fn js(src: &str) -> String {
+    // Dropping whitespace next to these can never join two tokens into one.
+    let is_punct = |c: char| "{}()[];,=:<>?!&|*".contains(c);
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut pending_space = false;
+    let mut pending_newline = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    pending_newline |= c == '\n';
+                    prev = c;
+                }
+                pending_space = true;
+            }
+            '\n' => pending_newline = true,
+            c if c.is_whitespace() => pending_space = true,
+            c => {
+                let last = out.chars().last();
+                if let Some(last) = last {
+                    // Newlines are kept where automatic semicolon insertion may rely on them.
+                    if pending_newline && !"{;,([".contains(last) && !"})]".contains(c) {
+                        out.push('\n');
+                    } else if (pending_space || pending_newline) && !is_punct(last) && !is_punct(c) {
+                        out.push(' ');
+                    }
+                }
+                pending_space = false;
+                pending_newline = false;
+
+                let starts_regex = c == '/' && regex_allowed(&out);
+                out.push(c);
+
+                if matches!(c, '"' | '\'') {
+                    copy_string(&mut out, &mut chars, c);
+                } else if c == '`' {
+                    copy_template(&mut out, &mut chars);
+                } else if starts_regex {
+                    copy_regex(&mut out, &mut chars);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Copies up to and including the closing `quote`, honouring backslash escapes.
+fn copy_string(out: &mut String, chars: &mut impl Iterator<Item = char>, quote: char) {
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                out.push(escaped);
+            }
+        } else if c == quote {
+            break;
+        }
+    }
+}
+
+/// Copies up to and including the closing backtick. `${}` substitutions are copied
+/// verbatim, along with any strings and templates nested inside them.
+fn copy_template(out: &mut String, chars: &mut Peekable<impl Iterator<Item = char>>) {
+    while let Some(c) = chars.next() {
+        out.push(c);
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    out.push(escaped);
+                }
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                out.push('{');
+                chars.next();
+                copy_substitution(out, chars);
+            }
+            '`' => break,
+            _ => {}
+        }
+    }
+}
+
+/// Copies the expression inside `${` through its matching `}`.
+fn copy_substitution(out: &mut String, chars: &mut Peekable<impl Iterator<Item = char>>) {
+    let mut depth = 1;
+
+    while let Some(c) = chars.next() {
+        out.push(c);
+        match c {
+            '"' | '\'' => copy_string(out, chars, c),
+            '`' => copy_template(out, chars),
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Copies a regex literal body up to its closing `/`, which may appear unescaped in a class.
+fn copy_regex(out: &mut String, chars: &mut impl Iterator<Item = char>) {
+    let mut in_class = false;
+
+    while let Some(c) = chars.next() {
+        out.push(c);
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    out.push(escaped);
+                }
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => break,
+            '\n' => break,
+            _ => {}
+        }
+    }
+}
+
+/// Whether a `/` following `out` begins a regex literal rather than a division.
+fn regex_allowed(out: &str) -> bool {
+    let out = out.trim_end();
+    let Some(last) = out.chars().last() else {
+        return true;
+    };
+
+    let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '$');
+    if is_word(last) {
+        let word: String = out.chars().rev().take_while(|&c| is_word(c)).collect();
+        return REGEX_KEYWORDS.iter().any(|keyword| keyword.chars().rev().eq(word.chars()));
+    }
+
+    !matches!(last, ')' | ']' | '"' | '\'' | '`')
+}
+
+/// Length of the tag at the start of `rest`, through its `>`, skipping quoted values.
+fn tag_end(rest: &str) -> usize {
+    let mut quote = None;
+
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+
+    rest.len()
+}
+
+/// A `<script>` tag without a `type`, or with a JavaScript one.
+fn is_javascript(tag: &str) -> bool {
+    let Some(script_type) = attribute(tag, "type") else {
+        return true;
+    };
+
+    let essence = script_type.split(';').next().unwrap_or("").trim();
+    essence.is_empty() || JS_SCRIPT_TYPES.iter().any(|js| essence.eq_ignore_ascii_case(js))
+}
+
+/// The value of `name` in `tag`, quoted or not.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag.get(1 + tag_name(tag).len()..)?;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let end = rest.find(|c: char| c.is_whitespace() || c == '=' || c == '>')?;
+        let (key, after) = rest.split_at(end);
+        if key.is_empty() {
+            return None;
+        }
+
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let close = value[1..].find(quote).map_or(value.len(), |i| i + 1);
+                        (&value[1..close], value.get(close + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(value.len());
+                        value.split_at(end)
+                    }
+                }
+            }
+            None => ("", after),
+        };
+
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+        rest = remaining;
+    }
+}
+
+fn tag_name(tag: &str) -> &str {
+    let name = tag.trim_start_matches('<');
+    let end = name
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+/// Collapses whitespace outside quoted attribute values.
+fn push_tag(out: &mut String, tag: &str) {
+    let mut quote = None;
+    let mut pending_space = false;
+
+    for c in tag.chars() {
+        if quote.is_none() && c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && c != '>' && !(c == '/' && tag.ends_with("/>")) {
+            out.push(' ');
+        }
+        pending_space = false;
+
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            _ => {}
+        }
+        out.push(c);
+    }
+}
+
+/// Text between tags, with every whitespace run reduced to one space.
+fn push_collapsed(out: &mut String, text: &str) {
+    let mut pending_space = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+        } else {
+            if pending_space && !out.ends_with(' ') {
+                out.push(' ');
+            }
+            pending_space = false;
+            out.push(c);
+        }
+    }
+
+    if pending_space && !out.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_keeps_preformatted_text() {
+        let src = "<!DOCTYPE html>\n<html>\n  <!-- note -->\n  <body  class=\"a  b\">\n    <p>Hello\n      world</p>\n    <pre>  keep\n   this </pre>\n    <textarea>\n  and this</textarea>\n  </body>\n</html>\n";
+
+        assert_eq!(
+            html(src),
+            "<!DOCTYPE html> <html> <body class=\"a  b\"> <p>Hello world</p> <pre>  keep\n   this </pre> <textarea>\n  and this</textarea> </body> </html>"
+        );
+    }
+
+    #[test]
+    fn css_strips_comments_but_not_strings() {
+        let src = "/* theme */\nbody {\n  color: red;\n  content: \"a  /* b */\";\n}\n\na > b,\nc { margin: 0 auto; }\n";
+
+        assert_eq!(css(src), "body{color: red;content: \"a  /* b */\"}a>b,c{margin: 0 auto}");
+    }
+
+    #[test]
+    fn js_keeps_strings_regexes_and_line_breaks() {
+        let src = "// setup\nconst url = \"http://x\"; /* c */\nlet re = /[/]\\/*/g;\nlet n = a / b\nreturn /x/.test(`  ${n}  `)\n";
+
+        assert_eq!(
+            js(src),
+            "const url=\"http://x\";let re=/[/]\\/*/g;let n=a / b\nreturn /x/.test(`  ${n}  `)"
+        );
+    }
+
+    #[test]
+    fn js_copies_nested_template_literals() {
+        let src = "let a = `${ok ? `http://x.com/a` : '}'}`;\nfoo(); // done\nlet b = `${ {x: 1}.x }` ;\n";
+
+        assert_eq!(js(src), "let a=`${ok ? `http://x.com/a` : '}'}`;foo();let b=`${ {x: 1}.x }`;");
+    }
+
+    #[test]
+    fn html_minifies_only_javascript_scripts() {
+        let src = "<script>\n  let a = 1; // x\n</script>\n<script type=\"module\">let b = 2;</script>\n<script type='text/x-template'><div>  // x</div></script>\n<script defer type=application/ld+json>{ \"a\": 1 }</script>";
+
+        assert_eq!(
+            html(src),
+            "<script>let a=1;</script> <script type=\"module\">let b=2;</script> <script type='text/x-template'><div>  // x</div></script> <script defer type=application/ld+json>{ \"a\": 1 }</script>"
+        );
+    }
+}
@@ -1,3 +1,27 @@
 pub mod plugin;
 pub mod core;
-pub mod file_cache;
\ No newline at end of file
+pub mod assets;
+pub mod client_ip;
+pub mod config;
+pub mod context;
+pub mod cookie;
+#[cfg(feature = "embed")]
+pub mod embedded;
+pub mod file_cache;
+pub mod form;
+pub mod glob;
+pub mod health;
+pub mod http_date;
+pub mod json;
+pub mod language;
+pub mod listener;
+pub mod live_reload;
+pub mod middleware;
+pub mod metrics;
+pub mod mime;
+pub mod minify;
+pub mod multipart;
+pub mod rate_limit;
+pub mod session;
+pub mod websocket;
+pub mod xml;
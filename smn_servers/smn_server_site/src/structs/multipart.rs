@@ -0,0 +1,428 @@
+use crate::structs::core::{Request, Response};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Upper bound on the headers of a single part.
+const MAX_PART_HEAD_SIZE: usize = 8 * 1024;
+
+static SPOOL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone)]
+pub struct MultipartLimits {
+    /// Largest whole `multipart/form-data` body accepted.
+    pub max_total_size: usize,
+    /// Largest plain (non-file) field value.
+    pub max_field_size: usize,
+    /// Largest single uploaded file.
+    pub max_file_size: usize,
+    /// Files bigger than this are spooled to `spool_dir` instead of kept in memory.
+    pub spool_threshold: usize,
+    pub spool_dir: PathBuf,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_total_size: 64 * 1024 * 1024,
+            max_field_size: 64 * 1024,
+            max_file_size: 32 * 1024 * 1024,
+            spool_threshold: 256 * 1024,
+            spool_dir: env::temp_dir(),
+        }
+    }
+}
+
+/// A parsed `multipart/form-data` body.
+#[derive(Default)]
+pub struct MultipartForm {
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<UploadedFile>,
+}
+
+pub struct UploadedFile {
+    pub field: String,
+    /// As sent by the client; sanitize before using it as a path.
+    pub file_name: String,
+    pub content_type: String,
+    pub size: usize,
+    data: FileData,
+}
+
+enum FileData {
+    Memory(Vec<u8>),
+    Spooled(SpoolFile),
+}
+
+impl UploadedFile {
+    /// Writes the upload to `dest`, moving the spool file where possible.
+    pub fn persist(&self, dest: &Path) -> io::Result<()> {
+        match &self.data {
+            FileData::Memory(bytes) => fs::write(dest, bytes),
+            FileData::Spooled(spool) => {
+                spool.file.sync_all()?;
+                fs::rename(&spool.path, dest)
+                    .or_else(|_| fs::copy(&spool.path, dest).map(|_| ()))
+            }
+        }
+    }
+}
+
+/// Temporary file removed when the upload is dropped.
+struct SpoolFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl SpoolFile {
+    fn create(dir: &Path) -> io::Result<Self> {
+        let path = dir.join(format!(
+            "smn-upload-{}-{}",
+            process::id(),
+            SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        // Already gone if `persist` renamed it
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+enum State {
+    Preamble,
+    AfterDelimiter,
+    Headers,
+    Body,
+    Done,
+}
+
+/// The part currently being received.
+struct Part {
+    field: String,
+    file_name: Option<String>,
+    content_type: String,
+    size: usize,
+    data: FileData,
+}
+
+/// Push parser fed with body chunks as they arrive, so large uploads are never
+/// held in memory whole.
+pub struct MultipartParser {
+    limits: MultipartLimits,
+    /// `\r\n--boundary`; a `\r\n` is fed first so the opening delimiter matches too.
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    state: State,
+    part: Option<Part>,
+    form: MultipartForm,
+}
+
+impl MultipartParser {
+    /// A parser for `multipart/form-data` requests, or `None` for any other body.
+    pub fn for_request(req: &Request, limits: &MultipartLimits) -> Option<Self> {
+        let content_type = req.header("Content-Type")?;
+        let (mime, params) = content_type.split_once(';')?;
+
+        if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+            return None;
+        }
+
+        let boundary = params.split(';').find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("boundary")
+                .then(|| value.trim().trim_matches('"').to_string())
+        })?;
+
+        if boundary.is_empty() || boundary.len() > 70 {
+            return None;
+        }
+
+        Some(Self {
+            limits: limits.clone(),
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            buffer: b"\r\n".to_vec(),
+            state: State::Preamble,
+            part: None,
+            form: MultipartForm::default(),
+        })
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), Response> {
+        self.buffer.extend_from_slice(chunk);
+
+        loop {
+            match self.state {
+                State::Preamble => match find(&self.buffer, &self.delimiter) {
+                    Some(index) => {
+                        self.buffer.drain(..index + self.delimiter.len());
+                        self.state = State::AfterDelimiter;
+                    }
+                    None => {
+                        let keep = self.delimiter.len() - 1;
+                        let drop = self.buffer.len().saturating_sub(keep);
+                        self.buffer.drain(..drop);
+                        return Ok(());
+                    }
+                },
+                State::AfterDelimiter => {
+                    if self.buffer.len() < 2 {
+                        return Ok(());
+                    }
+
+                    match &self.buffer[..2] {
+                        b"--" => self.state = State::Done,
+                        b"\r\n" => self.state = State::Headers,
+                        _ => return Err(Response::new(400)),
+                    }
+                    self.buffer.drain(..2);
+                }
+                State::Headers => {
+                    let Some(end) = find(&self.buffer, b"\r\n\r\n") else {
+                        if self.buffer.len() > MAX_PART_HEAD_SIZE {
+                            return Err(Response::new(400));
+                        }
+                        return Ok(());
+                    };
+
+                    let head = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+                    self.buffer.drain(..end + 4);
+                    self.part = Some(parse_part_head(&head)?);
+                    self.state = State::Body;
+                }
+                State::Body => match find(&self.buffer, &self.delimiter) {
+                    Some(index) => {
+                        let rest = self.buffer.split_off(index);
+                        let data = std::mem::replace(&mut self.buffer, rest);
+                        self.write_part(&data)?;
+                        self.buffer.drain(..self.delimiter.len());
+                        self.finish_part()?;
+                        self.state = State::AfterDelimiter;
+                    }
+                    None => {
+                        // Hold back enough bytes to catch a delimiter split across chunks
+                        let safe = self.buffer.len().saturating_sub(self.delimiter.len() - 1);
+                        let rest = self.buffer.split_off(safe);
+                        let data = std::mem::replace(&mut self.buffer, rest);
+                        self.write_part(&data)?;
+                        return Ok(());
+                    }
+                },
+                State::Done => {
+                    self.buffer.clear();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// The parsed form once the whole body has been fed.
+    pub fn finish(self) -> Result<MultipartForm, Response> {
+        match self.state {
+            State::Done => Ok(self.form),
+            _ => Err(Response::new(400)),
+        }
+    }
+
+    fn write_part(&mut self, data: &[u8]) -> Result<(), Response> {
+        let Some(part) = self.part.as_mut() else {
+            return Ok(());
+        };
+
+        part.size += data.len();
+
+        let limit = match part.file_name {
+            Some(_) => self.limits.max_file_size,
+            None => self.limits.max_field_size,
+        };
+        if part.size > limit {
+            return Err(Response::new(413));
+        }
+
+        let spill = part.file_name.is_some()
+            && matches!(&part.data, FileData::Memory(bytes) if bytes.len() + data.len() > self.limits.spool_threshold);
+
+        if spill {
+            let mut spool = SpoolFile::create(&self.limits.spool_dir).map_err(|_| Response::new(500))?;
+            if let FileData::Memory(bytes) = &part.data {
+                spool.file.write_all(bytes).map_err(|_| Response::new(500))?;
+            }
+            part.data = FileData::Spooled(spool);
+        }
+
+        match &mut part.data {
+            FileData::Memory(bytes) => bytes.extend_from_slice(data),
+            FileData::Spooled(spool) => spool.file.write_all(data).map_err(|_| Response::new(500))?,
+        }
+
+        Ok(())
+    }
+
+    fn finish_part(&mut self) -> Result<(), Response> {
+        let Some(part) = self.part.take() else {
+            return Ok(());
+        };
+
+        match part.file_name {
+            Some(file_name) => self.form.files.push(UploadedFile {
+                field: part.field,
+                file_name,
+                content_type: part.content_type,
+                size: part.size,
+                data: part.data,
+            }),
+            None => {
+                let FileData::Memory(bytes) = part.data else {
+                    return Err(Response::new(500));
+                };
+                self.form
+                    .fields
+                    .push((part.field, String::from_utf8_lossy(&bytes).to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_part_head(head: &str) -> Result<Part, Response> {
+    let mut field = None;
+    let mut file_name = None;
+    let mut content_type = "application/octet-stream".to_string();
+
+    for line in head.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        if name.trim().eq_ignore_ascii_case("Content-Type") {
+            content_type = value.trim().to_string();
+        }
+
+        if !name.trim().eq_ignore_ascii_case("Content-Disposition") {
+            continue;
+        }
+
+        for param in value.split(';').skip(1) {
+            let Some((key, value)) = param.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"').to_string();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => field = Some(value),
+                "filename" => file_name = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(Part {
+        field: field.ok_or_else(|| Response::new(400))?,
+        file_name,
+        content_type,
+        size: 0,
+        data: FileData::Memory(Vec::new()),
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{RequestBuilder, ScratchDir};
+
+    const BODY: &[u8] = b"preamble\r\n--xyz\r\n\
+Content-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n--xyz\r\n\
+Content-Disposition: form-data; name=\"doc\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\n\
+line one\r\n--xy not yet\r\n--xyz--\r\n";
+
+    /// Feeds `chunks` in order, returning the form or the refusing status.
+    fn parse(chunks: &[&[u8]], limits: &MultipartLimits) -> Result<MultipartForm, u16> {
+        let req = RequestBuilder::post("/")
+            .header("Content-Type", "multipart/form-data; boundary=xyz")
+            .build();
+        let mut parser = MultipartParser::for_request(&req, limits).unwrap();
+
+        for chunk in chunks {
+            parser.feed(chunk).map_err(|resp| resp.status)?;
+        }
+        parser.finish().map_err(|resp| resp.status)
+    }
+
+    fn contents(file: &UploadedFile) -> Vec<u8> {
+        match &file.data {
+            FileData::Memory(bytes) => bytes.clone(),
+            FileData::Spooled(spool) => fs::read(&spool.path).unwrap(),
+        }
+    }
+
+    #[track_caller]
+    fn assert_parsed(form: Result<MultipartForm, u16>) -> MultipartForm {
+        let Ok(form) = form else {
+            panic!("refused with {:?}", form.err());
+        };
+        assert_eq!(form.fields, [("title".to_string(), "hello".to_string())]);
+        assert_eq!(form.files.len(), 1);
+
+        let file = &form.files[0];
+        assert_eq!((file.field.as_str(), file.file_name.as_str()), ("doc", "a.txt"));
+        assert_eq!(file.content_type, "text/plain");
+        assert_eq!(contents(file), b"line one\r\n--xy not yet");
+        assert_eq!(file.size, contents(file).len());
+        form
+    }
+
+    #[test]
+    fn chunk_boundaries_do_not_matter() {
+        let limits = MultipartLimits::default();
+
+        let bytes: Vec<&[u8]> = BODY.chunks(1).collect();
+        assert_parsed(parse(&bytes, &limits));
+
+        // Every split point, including each one inside a delimiter held back in `State::Body`
+        for at in 0..=BODY.len() {
+            assert_parsed(parse(&[&BODY[..at], &BODY[at..]], &limits));
+        }
+    }
+
+    #[test]
+    fn missing_closing_delimiter_is_refused() {
+        let truncated = &BODY[..BODY.len() - b"--xyz--\r\n".len()];
+
+        assert_eq!(parse(&[truncated], &MultipartLimits::default()).err(), Some(400));
+    }
+
+    #[test]
+    fn large_files_spill_to_a_spool_file_removed_on_drop() {
+        let spool_dir = ScratchDir::new("multipart-spool");
+        let limits = MultipartLimits {
+            spool_threshold: 8,
+            spool_dir: spool_dir.path().to_path_buf(),
+            ..MultipartLimits::default()
+        };
+
+        let bytes: Vec<&[u8]> = BODY.chunks(3).collect();
+        let form = assert_parsed(parse(&bytes, &limits));
+
+        let FileData::Spooled(spool) = &form.files[0].data else {
+            panic!("file past the threshold was kept in memory");
+        };
+        assert!(spool.path.starts_with(spool_dir.path()));
+        assert_eq!(fs::read_dir(spool_dir.path()).unwrap().count(), 1);
+
+        drop(form);
+        assert_eq!(fs::read_dir(spool_dir.path()).unwrap().count(), 0);
+    }
+}
@@ -1,10 +1,104 @@
+use crate::structs::context::PluginContext;
 use crate::structs::core::{Request, Response};
+use crate::structs::websocket::WebSocketHandler;
 
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
 
+pub trait Plugin: Send + Sync {
+    fn plugin_name(&self) -> &str;
+    fn plugin_init(&self, _ctx: &PluginContext) {}
+    fn plugin_match(&self, req: &Request, ctx: &PluginContext) -> bool;
+    fn plugin_serve(&self, req: &Request, ctx: &PluginContext) -> Response;
+
+    /// Opts a matched request into streaming `multipart/form-data` parsing, which may
+    /// spool files to disk. Multipart bodies for plugins that decline are refused with
+    /// `415` before any of the body is read.
+    fn plugin_accepts_multipart(&self, _req: &Request) -> bool {
+        false
+    }
+
+    /// Accepts a matched `Upgrade: websocket` request by returning a handler.
+    /// Plugins that return `None` are served through `plugin_serve` as usual.
+    fn plugin_websocket(&self, _req: &Request, _ctx: &PluginContext) -> Option<WebSocketHandler> {
+        None
+    }
+}
+
+#[cfg(feature = "async")]
+pub type PluginFuture<'a> = Pin<Box<dyn Future<Output = Response> + Send + 'a>>;
 
-pub trait Plugin {
+/// Async flavour of `Plugin` for I/O-bound work, served by the tokio backend.
+#[cfg(feature = "async")]
+pub trait AsyncPlugin: Send + Sync {
     fn plugin_name(&self) -> &str;
-    fn plugin_init(&self) {}
-    fn plugin_match(&self, req: &Request) -> bool;
-    fn plugin_serve(&self, req: &Request) -> Response;
+    fn plugin_init(&self, _ctx: &PluginContext) {}
+    fn plugin_match(&self, req: &Request, ctx: &PluginContext) -> bool;
+    fn plugin_serve<'a>(&'a self, req: &'a Request, ctx: &'a PluginContext) -> PluginFuture<'a>;
+
+    /// See `Plugin::plugin_accepts_multipart`.
+    fn plugin_accepts_multipart(&self, _req: &Request) -> bool {
+        false
+    }
+}
+
+/// A plugin registered with the server, in either flavour.
+pub enum PluginEntry {
+    Sync(Box<dyn Plugin>),
+    #[cfg(feature = "async")]
+    Async(Box<dyn AsyncPlugin>),
+}
+
+impl<P: Plugin + 'static> From<P> for PluginEntry {
+    fn from(plugin: P) -> Self {
+        PluginEntry::Sync(Box::new(plugin))
+    }
+}
+
+impl PluginEntry {
+    #[cfg(feature = "async")]
+    pub fn from_async<P: AsyncPlugin + 'static>(plugin: P) -> Self {
+        PluginEntry::Async(Box::new(plugin))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            PluginEntry::Sync(plugin) => plugin.plugin_name(),
+            #[cfg(feature = "async")]
+            PluginEntry::Async(plugin) => plugin.plugin_name(),
+        }
+    }
+
+    pub fn init(&self, ctx: &PluginContext) {
+        match self {
+            PluginEntry::Sync(plugin) => plugin.plugin_init(ctx),
+            #[cfg(feature = "async")]
+            PluginEntry::Async(plugin) => plugin.plugin_init(ctx),
+        }
+    }
+
+    pub fn matches(&self, req: &Request, ctx: &PluginContext) -> bool {
+        match self {
+            PluginEntry::Sync(plugin) => plugin.plugin_match(req, ctx),
+            #[cfg(feature = "async")]
+            PluginEntry::Async(plugin) => plugin.plugin_match(req, ctx),
+        }
+    }
+
+    pub fn accepts_multipart(&self, req: &Request) -> bool {
+        match self {
+            PluginEntry::Sync(plugin) => plugin.plugin_accepts_multipart(req),
+            #[cfg(feature = "async")]
+            PluginEntry::Async(plugin) => plugin.plugin_accepts_multipart(req),
+        }
+    }
+
+    /// WebSocket upgrades are only offered to sync plugins.
+    pub fn websocket(&self, req: &Request, ctx: &PluginContext) -> Option<WebSocketHandler> {
+        match self {
+            PluginEntry::Sync(plugin) => plugin.plugin_websocket(req, ctx),
+            #[cfg(feature = "async")]
+            PluginEntry::Async(_) => None,
+        }
+    }
 }
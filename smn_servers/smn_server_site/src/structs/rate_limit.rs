@@ -0,0 +1,105 @@
+use crate::structs::core::Response;
+use smn_shared::rate_limit::TokenBuckets;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+pub use smn_shared::rate_limit::RateLimitRule;
+
+pub struct RateLimitConfig {
+    /// The longest matching prefix applies; paths matching no rule are unlimited.
+    pub rules: Vec<RateLimitRule>,
+    /// Simultaneous connections allowed per client; 0 disables the cap.
+    pub max_connections_per_ip: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![RateLimitRule {
+                path_prefix: "/".to_string(),
+                burst: 100,
+                per_second: 20.0,
+            }],
+            max_connections_per_ip: 32,
+        }
+    }
+}
+
+/// Token buckets keyed by rule and client address.
+pub struct RateLimiter {
+    buckets: TokenBuckets,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            buckets: TokenBuckets::new(config.rules.clone()),
+        }
+    }
+
+    /// Takes a token for the client, or returns a `429` to send instead.
+    pub fn check(&self, path: &str, client: IpAddr) -> Option<Response> {
+        let retry_after = self.buckets.take(path, client).err()?;
+
+        Some(
+            Response::new(429)
+                .with_header("Retry-After", &retry_after.to_string())
+                .with_header("Content-Type", "text/plain; charset=utf-8")
+                .with_body("Too many requests"),
+        )
+    }
+}
+
+/// Counts open connections per client address.
+pub struct ConnectionLimiter {
+    max_per_ip: usize,
+    open: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+/// Holds one of a client's connection slots until dropped.
+pub struct ConnectionSlot {
+    client: IpAddr,
+    open: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut open = self.open.lock().unwrap();
+
+        if let Some(count) = open.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.client);
+            }
+        }
+    }
+}
+
+impl ConnectionLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            max_per_ip: config.max_connections_per_ip,
+            open: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn acquire(&self, client: IpAddr) -> Result<ConnectionSlot, Response> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(client).or_insert(0);
+
+        if self.max_per_ip > 0 && *count >= self.max_per_ip {
+            return Err(Response::new(429)
+                .with_header("Retry-After", "1")
+                .with_header("Content-Type", "text/plain; charset=utf-8")
+                .with_body("Too many connections"));
+        }
+
+        *count += 1;
+
+        Ok(ConnectionSlot {
+            client,
+            open: Arc::clone(&self.open),
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A visitor's server-side session, attached to `Request::session` by `MiddlewareSession`.
+/// Changes made while serving are saved once the response goes out.
+#[derive(Clone, Default)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+}
+
+#[derive(Default)]
+struct SessionState {
+    /// `None` until the session is first saved, and again once it is destroyed or
+    /// regenerated so the next save issues a fresh id.
+    id: Option<String>,
+    /// A previous id whose stored copy must be removed.
+    retired: Option<String>,
+    values: HashMap<String, String>,
+    changed: bool,
+    destroyed: bool,
+}
+
+/// What the session middleware has to persist after a request.
+pub enum SessionChange {
+    Unchanged,
+    Save {
+        id: Option<String>,
+        values: HashMap<String, String>,
+        /// Stored under an id that is no longer in use.
+        retired: Option<String>,
+    },
+    Destroy {
+        id: Option<String>,
+    },
+}
+
+impl Session {
+    pub fn restore(id: String, values: HashMap<String, String>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SessionState {
+                id: Some(id),
+                values,
+                ..SessionState::default()
+            })),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.state.lock().unwrap().values.get(key).cloned()
+    }
+
+    pub fn set(&self, key: &str, value: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.values.insert(key.to_string(), value.to_string());
+        state.changed = true;
+        state.destroyed = false;
+    }
+
+    /// Drops every value and expires the session cookie, e.g. on logout. Anything set
+    /// afterwards starts a new session under a new id.
+    pub fn destroy(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.retire_id();
+        state.values.clear();
+        state.changed = false;
+        state.destroyed = true;
+    }
+
+    /// Keeps the values under a new id, e.g. after logging in, so an id planted
+    /// before authentication is useless afterwards.
+    pub fn regenerate(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.retire_id();
+        state.changed = true;
+    }
+
+    pub fn take_change(&self) -> SessionChange {
+        let mut state = self.state.lock().unwrap();
+
+        if state.destroyed {
+            state.destroyed = false;
+            SessionChange::Destroy { id: state.retired.take() }
+        } else if state.changed {
+            state.changed = false;
+            SessionChange::Save {
+                id: state.id.clone(),
+                values: state.values.clone(),
+                retired: state.retired.take(),
+            }
+        } else {
+            SessionChange::Unchanged
+        }
+    }
+}
+
+impl SessionState {
+    fn retire_id(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.retired = Some(id);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::structs::core::Request;
+use crate::structs::listener::Connection;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha1::{Digest, Sha1};
+use std::io::{self, Read, Write};
+
+/// GUID appended to the client key during the RFC 6455 handshake.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest assembled message accepted from a client.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+pub const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_INVALID_DATA: u16 = 1007;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// Called on its own thread once the handshake has completed.
+pub type WebSocketHandler = Box<dyn FnOnce(WebSocket) + Send + 'static>;
+
+#[derive(Debug)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Close(u16, String),
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// True if the request asks to switch protocols to WebSocket.
+pub fn is_upgrade_request(req: &Request) -> bool {
+    req.method == "GET"
+        && req.header_has_token("Connection", "upgrade")
+        && req.header_has_token("Upgrade", "websocket")
+}
+
+/// Builds the `101 Switching Protocols` reply, or `None` if the handshake is invalid.
+pub fn handshake_response(req: &Request) -> Option<String> {
+    if req.header("Sec-WebSocket-Version") != Some("13") {
+        return None;
+    }
+
+    let key = req.header("Sec-WebSocket-Key")?;
+
+    Some(format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+Upgrade: websocket\r\n\
+Connection: Upgrade\r\n\
+Sec-WebSocket-Accept: {}\r\n\
+\r\n",
+        accept_key(key)
+    ))
+}
+
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(HANDSHAKE_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+/// Message-oriented handle over an upgraded connection.
+///
+/// Pings are answered and fragmented messages reassembled inside `recv`,
+/// so handlers only ever see whole text, binary or close messages.
+pub struct WebSocket<S = Connection> {
+    stream: S,
+    closed: bool,
+}
+
+impl<S: Read + Write> WebSocket<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            closed: false,
+        }
+    }
+
+    /// Blocks until the next complete message arrives.
+    pub fn recv(&mut self) -> io::Result<Message> {
+        if self.closed {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "websocket closed"));
+        }
+
+        let mut fragments: Option<(u8, Vec<u8>)> = None;
+
+        loop {
+            let frame = self.read_frame()?;
+
+            match frame.opcode {
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    let (opcode, mut data) = match (frame.opcode, fragments.take()) {
+                        (OP_CONTINUATION, Some(started)) => started,
+                        (OP_CONTINUATION, None) => {
+                            return self.fail(CLOSE_PROTOCOL_ERROR, "unexpected continuation");
+                        }
+                        (_, Some(_)) => {
+                            return self.fail(CLOSE_PROTOCOL_ERROR, "interleaved message");
+                        }
+                        (opcode, None) => (opcode, Vec::new()),
+                    };
+
+                    if data.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+                        return self.fail(CLOSE_TOO_BIG, "message too large");
+                    }
+                    data.extend_from_slice(&frame.payload);
+
+                    if !frame.fin {
+                        fragments = Some((opcode, data));
+                        continue;
+                    }
+
+                    if opcode == OP_BINARY {
+                        return Ok(Message::Binary(data));
+                    }
+
+                    return match String::from_utf8(data) {
+                        Ok(text) => Ok(Message::Text(text)),
+                        Err(_) => self.fail(CLOSE_INVALID_DATA, "invalid utf-8"),
+                    };
+                }
+                OP_PING => self.write_frame(OP_PONG, &frame.payload)?,
+                OP_PONG => {}
+                OP_CLOSE => {
+                    let (code, reason) = match frame.payload.as_slice() {
+                        [] => (CLOSE_NORMAL, String::new()),
+                        // A close payload starts with a two-byte status code
+                        [_] => return self.fail(CLOSE_PROTOCOL_ERROR, "truncated close code"),
+                        [high, low, reason @ ..] => match String::from_utf8(reason.to_vec()) {
+                            Ok(reason) => (u16::from_be_bytes([*high, *low]), reason),
+                            Err(_) => return self.fail(CLOSE_INVALID_DATA, "invalid utf-8"),
+                        },
+                    };
+
+                    // Echo the close so the client can shut down cleanly
+                    let _ = self.close(code, "");
+                    return Ok(Message::Close(code, reason));
+                }
+                _ => return self.fail(CLOSE_PROTOCOL_ERROR, "unknown opcode"),
+            }
+        }
+    }
+
+    pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.write_frame(OP_TEXT, text.as_bytes())
+    }
+
+    pub fn send_binary(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write_frame(OP_BINARY, data)
+    }
+
+    /// Sends a close frame; further sends and receives will fail. The reason is cut
+    /// to fit a control frame, at a character boundary.
+    pub fn close(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+
+        let mut end = reason.len().min(123);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(&reason.as_bytes()[..end]);
+
+        let result = self.write_frame(OP_CLOSE, &payload);
+        self.closed = true;
+        result
+    }
+
+    fn fail<T>(&mut self, code: u16, reason: &str) -> io::Result<T> {
+        let _ = self.close(code, reason);
+        Err(io::Error::new(io::ErrorKind::InvalidData, reason.to_string()))
+    }
+
+    fn read_frame(&mut self) -> io::Result<Frame> {
+        let mut head = [0_u8; 2];
+        self.stream.read_exact(&mut head)?;
+
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+
+        if head[0] & 0x70 != 0 {
+            return self.fail(CLOSE_PROTOCOL_ERROR, "reserved bits set");
+        }
+
+        // Clients must mask every frame (RFC 6455 section 5.1)
+        if !masked {
+            return self.fail(CLOSE_PROTOCOL_ERROR, "unmasked client frame");
+        }
+
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut ext = [0_u8; 2];
+                self.stream.read_exact(&mut ext)?;
+                u16::from_be_bytes(ext) as u64
+            }
+            127 => {
+                let mut ext = [0_u8; 8];
+                self.stream.read_exact(&mut ext)?;
+                u64::from_be_bytes(ext)
+            }
+            short => short as u64,
+        };
+
+        let is_control = opcode & 0x8 != 0;
+        if is_control && (len > 125 || !fin) {
+            return self.fail(CLOSE_PROTOCOL_ERROR, "invalid control frame");
+        }
+
+        if len > MAX_MESSAGE_SIZE as u64 {
+            return self.fail(CLOSE_TOO_BIG, "frame too large");
+        }
+
+        let mut mask = [0_u8; 4];
+        self.stream.read_exact(&mut mask)?;
+
+        let mut payload = vec![0_u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        Ok(Frame {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        if self.closed {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "websocket closed"));
+        }
+
+        let mut frame = Vec::with_capacity(payload.len() + 10);
+        frame.push(0x80 | opcode);
+
+        // Server frames are never masked
+        match payload.len() {
+            len if len < 126 => frame.push(len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockStream, RequestBuilder};
+
+    /// A masked client frame, as browsers send them.
+    fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![first];
+
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        frame
+    }
+
+    fn receiving(frames: &[Vec<u8>]) -> WebSocket<MockStream> {
+        WebSocket::new(MockStream::new(frames.concat()))
+    }
+
+    #[test]
+    fn handshake_matches_rfc_6455() {
+        let req = RequestBuilder::get("/ws")
+            .header("Connection", "keep-alive, Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .build();
+
+        assert!(is_upgrade_request(&req));
+        let response = handshake_response(&req).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101 "));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let old_version = RequestBuilder::get("/ws")
+            .header("Sec-WebSocket-Version", "8")
+            .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .build();
+        assert!(handshake_response(&old_version).is_none());
+    }
+
+    #[test]
+    fn fragments_are_reassembled_around_pings() {
+        let mut socket = receiving(&[
+            client_frame(OP_TEXT, b"Hel"),
+            client_frame(0x80 | OP_PING, b"hi"),
+            client_frame(0x80 | OP_CONTINUATION, b"lo"),
+            client_frame(0x80 | OP_BINARY, &[7; 300]),
+        ]);
+
+        assert!(matches!(socket.recv(), Ok(Message::Text(text)) if text == "Hello"));
+        assert!(matches!(socket.recv(), Ok(Message::Binary(data)) if data == [7; 300]));
+        assert_eq!(socket.stream.output, [0x80 | OP_PONG, 2, b'h', b'i']);
+    }
+
+    #[test]
+    fn protocol_errors_close_with_1002() {
+        let unmasked = vec![0x80 | OP_TEXT, 1, b'x'];
+        let truncated_close = client_frame(0x80 | OP_CLOSE, &[0x03]);
+
+        for frame in [unmasked, truncated_close] {
+            let mut socket = receiving(&[frame]);
+
+            assert!(socket.recv().is_err());
+            assert_eq!(socket.stream.output[0], 0x80 | OP_CLOSE);
+            assert_eq!(socket.stream.output[2..4], CLOSE_PROTOCOL_ERROR.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn close_echoes_the_code_and_cuts_long_reasons_at_a_character() {
+        let mut socket = receiving(&[client_frame(0x80 | OP_CLOSE, &[0x03, 0xE9, b'b', b'y', b'e'])]);
+        assert!(matches!(socket.recv(), Ok(Message::Close(1001, reason)) if reason == "bye"));
+        assert_eq!(socket.stream.output, [0x80 | OP_CLOSE, 2, 0x03, 0xE9]);
+
+        let mut socket = receiving(&[]);
+        socket.close(CLOSE_NORMAL, &"é".repeat(100)).unwrap();
+        let payload = &socket.stream.output[2..];
+        assert_eq!(payload.len(), 124);
+        assert!(std::str::from_utf8(&payload[2..]).is_ok());
+        assert!(socket.send_text("late").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+//! Request builders, an in-memory stream and response assertions for testing
+//! plugins and the `Server` pipeline without binding a port.
+//!
+//! Requests go through whichever backend is compiled in; with `async` each one is
+//! driven to completion on a shared tokio runtime.
+
+use crate::server::Server;
+use crate::structs::core::{Request, Response};
+use crate::structs::form::percent_encode;
+use std::env;
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[cfg(feature = "async")]
+use crate::structs::listener::{Connection, IntoConnection};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+
+/// Client address used unless a test picks another.
+pub const TEST_CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+/// The connection every test request arrives on.
+pub const TEST_PEER: SocketAddr = SocketAddr::new(TEST_CLIENT, 40000);
+
+pub struct RequestBuilder {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl RequestBuilder {
+    pub fn new(method: &str, path: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: vec![("Host".to_string(), "localhost".to_string())],
+            body: Vec::new(),
+        }
+    }
+
+    pub fn get(path: &str) -> Self {
+        Self::new("GET", path)
+    }
+
+    pub fn post(path: &str) -> Self {
+        Self::new("POST", path)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// An `application/x-www-form-urlencoded` body; values are encoded here.
+    pub fn form(self, fields: &[(&str, &str)]) -> Self {
+        let body = fields
+            .iter()
+            .map(|(name, value)| format!("{}={}", percent_encode(name), percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        self.header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+    }
+
+    /// A `multipart/form-data` body of `(field, file name, contents)` parts;
+    /// a `None` file name makes a plain field.
+    pub fn multipart(self, parts: &[(&str, Option<&str>, &[u8])]) -> Self {
+        let boundary = "smn-test-boundary";
+        let mut body = Vec::new();
+
+        for (field, file_name, contents) in parts {
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            match file_name {
+                Some(file_name) => body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+Content-Type: application/octet-stream\r\n\r\n",
+                        field, file_name
+                    )
+                    .as_bytes(),
+                ),
+                None => body.extend_from_slice(
+                    format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", field).as_bytes(),
+                ),
+            }
+            body.extend_from_slice(contents);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        self.header("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
+            .body(body)
+    }
+
+    /// The request as it would arrive on the wire, with `Content-Length` filled in.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("{} {} HTTP/1.1\r\n", self.method, self.path);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        let has_length = self.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Length"));
+        if !self.body.is_empty() && !has_length {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// A parsed `Request`, as plugins receive it.
+    pub fn build(self) -> Request {
+        let bytes = self.to_bytes();
+        let head_len = bytes.len() - self.body.len();
+
+        let mut req = Request::new(String::from_utf8_lossy(&bytes[..head_len]).to_string());
+        req.peer = Some(TEST_PEER);
+        req.client_ip = TEST_CLIENT;
+        req.body = self.body;
+        req
+    }
+}
+
+/// A fresh directory under the system temp dir, removed on drop so a failed
+/// assertion does not leave it behind.
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// `name` keeps tests apart and the process id keeps concurrent runs apart.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("smn-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("Failed to create scratch dir");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Reads from a fixed input and records everything written.
+pub struct MockStream {
+    input: Cursor<Vec<u8>>,
+    pub output: Vec<u8>,
+}
+
+impl MockStream {
+    pub fn new(input: Vec<u8>) -> Self {
+        Self {
+            input: Cursor::new(input),
+            output: Vec::new(),
+        }
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl tokio::io::AsyncRead for MockStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = self.input.read(buf.initialize_unfilled())?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl tokio::io::AsyncWrite for MockStream {
+    fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.output.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl IntoConnection for &mut MockStream {
+    fn into_connection(self) -> io::Result<Connection> {
+        panic!("request asked to take over the connection");
+    }
+}
+
+/// Sends the request over an in-memory stream through the full pipeline.
+pub fn send(server: &Server, req: RequestBuilder) -> TestResponse {
+    send_from(server, TEST_PEER, req)
+}
+
+/// `send`, arriving from another peer such as a local proxy.
+#[cfg(not(feature = "async"))]
+pub fn send_from(server: &Server, peer: SocketAddr, req: RequestBuilder) -> TestResponse {
+    let mut stream = MockStream::new(req.to_bytes());
+    let takeover = server.serve_stream(&mut stream, Some(peer));
+    assert!(takeover.is_none(), "request asked to take over the connection");
+
+    TestResponse::parse(&stream.output)
+}
+
+/// `send`, arriving from another peer such as a local proxy.
+#[cfg(feature = "async")]
+pub fn send_from(server: &Server, peer: SocketAddr, req: RequestBuilder) -> TestResponse {
+    let mut stream = MockStream::new(req.to_bytes());
+    RUNTIME.block_on(server.handle_client(&mut stream, Some(peer)));
+
+    TestResponse::parse(&stream.output)
+}
+
+/// Runs the request through the pipeline without serializing it.
+#[cfg(not(feature = "async"))]
+pub fn dispatch(server: &Server, req: RequestBuilder) -> TestResponse {
+    TestResponse::from(server.handle_request(req.build()))
+}
+
+/// Runs the request through the pipeline without serializing it.
+#[cfg(feature = "async")]
+pub fn dispatch(server: &Server, req: RequestBuilder) -> TestResponse {
+    TestResponse::from(RUNTIME.block_on(server.handle_request(req.build())))
+}
+
+/// Multi-threaded, since sync plugins are served through `block_in_place`.
+#[cfg(feature = "async")]
+static RUNTIME: std::sync::LazyLock<tokio::runtime::Runtime> = std::sync::LazyLock::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start tokio runtime")
+});
+
+/// A response read back from the wire or taken from the pipeline, with assertions.
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// Parses a serialized response, skipping any `100 Continue` interim responses.
+    pub fn parse(raw: &[u8]) -> Self {
+        let mut raw = raw;
+
+        loop {
+            let end = raw
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .expect("response has no end of headers");
+            let head = String::from_utf8_lossy(&raw[..end]).to_string();
+            let body = &raw[end + 4..];
+
+            let mut lines = head.lines();
+            let status = lines
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|code| code.parse().ok())
+                .expect("response has no status line");
+
+            if status == 100 {
+                raw = body;
+                continue;
+            }
+
+            let headers = lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .collect();
+
+            return Self {
+                status,
+                headers,
+                body: body.to_vec(),
+            };
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    #[track_caller]
+    pub fn assert_status(&self, status: u16) -> &Self {
+        assert_eq!(self.status, status, "unexpected status, body: {}", self.text());
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_header(&self, name: &str, value: &str) -> &Self {
+        assert_eq!(self.header(name), Some(value), "header {}", name);
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_body_contains(&self, needle: &str) -> &Self {
+        assert!(self.text().contains(needle), "body does not contain {:?}: {}", needle, self.text());
+        self
+    }
+}
+
+impl From<Response> for TestResponse {
+    fn from(resp: Response) -> Self {
+        Self {
+            status: resp.status,
+            headers: resp.headers,
+            body: resp.body.into_bytes(),
+        }
+    }
+}
+
@@ -0,0 +1,170 @@
+pub struct CorsConfig {
+    /// Exact origins such as `https://example.com`, `https://*.example.com` for any
+    /// subdomain, or `*` for every origin.
+    /// `*` cannot be combined with `allow_credentials`.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers a preflight may ask for, matched case-insensitively.
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// Seconds a browser may cache a preflight result.
+    pub max_age: u64,
+}
+
+/// Sent with every preflight answer, allowed or not.
+pub const PREFLIGHT_VARY: &str = "Origin, Access-Control-Request-Method, Access-Control-Request-Headers";
+
+/// Decides which cross-origin requests are allowed and the `Access-Control-*` headers
+/// that say so; each server applies them to its own response type.
+pub struct CorsPolicy {
+    config: CorsConfig,
+}
+
+impl CorsPolicy {
+    /// Refuses `*` alongside credentials, which browsers reject and which would
+    /// otherwise mean echoing back every origin with credentials allowed.
+    pub fn new(config: CorsConfig) -> Result<Self, String> {
+        if config.allow_credentials && config.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err("the `*` origin cannot be combined with allow_credentials".to_string());
+        }
+
+        Ok(Self { config })
+    }
+
+    /// Headers for an actual cross-origin response, or `None` when `origin` is not allowed.
+    pub fn response_headers(&self, origin: &str) -> Option<Vec<(&'static str, String)>> {
+        if !self.allows_origin(origin) {
+            return None;
+        }
+
+        let wildcard = self.config.allowed_origins.iter().any(|allowed| allowed == "*");
+        let mut headers = vec![("Access-Control-Allow-Origin", if wildcard { "*" } else { origin }.to_string())];
+
+        if self.config.allow_credentials {
+            headers.push(("Access-Control-Allow-Credentials", "true".to_string()));
+        }
+
+        Some(headers)
+    }
+
+    /// Headers answering a preflight, or `None` when it is refused; a refused preflight
+    /// carries no CORS headers and the browser blocks the request.
+    pub fn preflight_headers(
+        &self,
+        origin: &str,
+        method: &str,
+        requested_headers: &str,
+    ) -> Option<Vec<(&'static str, String)>> {
+        if !self.allows_method(method) || !self.allows_headers(requested_headers) {
+            return None;
+        }
+
+        let mut headers = self.response_headers(origin)?;
+        headers.push(("Access-Control-Allow-Methods", self.config.allowed_methods.join(", ")));
+        if !self.config.allowed_headers.is_empty() {
+            headers.push(("Access-Control-Allow-Headers", self.config.allowed_headers.join(", ")));
+        }
+        headers.push(("Access-Control-Max-Age", self.config.max_age.to_string()));
+        Some(headers)
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.config.allowed_origins.iter().any(|allowed| {
+            if allowed == "*" || allowed.eq_ignore_ascii_case(origin) {
+                return true;
+            }
+
+            // `https://*.example.com` matches `https://api.example.com`, not `https://example.com`
+            match (allowed.split_once("://*."), origin.split_once("://")) {
+                (Some((scheme, domain)), Some((origin_scheme, host))) => {
+                    scheme.eq_ignore_ascii_case(origin_scheme)
+                        && host.len() > domain.len()
+                        && host.to_ascii_lowercase().ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+                }
+                _ => false,
+            }
+        })
+    }
+
+    fn allows_method(&self, method: &str) -> bool {
+        self.config.allowed_methods.iter().any(|allowed| allowed == method)
+    }
+
+    fn allows_headers(&self, requested: &str) -> bool {
+        requested
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .all(|name| {
+                self.config
+                    .allowed_headers
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(name))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(origins: &[&str], allow_credentials: bool) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            allowed_headers: vec!["Content-Type".to_string()],
+            allow_credentials,
+            max_age: 600,
+        }
+    }
+
+    fn allowed_origin(policy: &CorsPolicy, origin: &str) -> Option<String> {
+        policy
+            .response_headers(origin)
+            .and_then(|headers| headers.into_iter().find(|(name, _)| *name == "Access-Control-Allow-Origin"))
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn wildcard_origin_refuses_credentials() {
+        assert!(CorsPolicy::new(config(&["*"], true)).is_err());
+        assert!(CorsPolicy::new(config(&["*"], false)).is_ok());
+        assert!(CorsPolicy::new(config(&["https://*.example.com"], true)).is_ok());
+    }
+
+    #[test]
+    fn origins_match_exactly_or_by_subdomain() {
+        let policy = CorsPolicy::new(config(&["https://app.test", "https://*.example.com"], true)).unwrap();
+
+        assert_eq!(allowed_origin(&policy, "HTTPS://APP.TEST").as_deref(), Some("HTTPS://APP.TEST"));
+        assert_eq!(allowed_origin(&policy, "https://api.Example.com").as_deref(), Some("https://api.Example.com"));
+        assert_eq!(allowed_origin(&policy, "https://example.com"), None);
+        assert_eq!(allowed_origin(&policy, "http://api.example.com"), None);
+        assert_eq!(allowed_origin(&policy, "https://evilexample.com"), None);
+        assert_eq!(
+            policy.response_headers("https://app.test").unwrap()[1],
+            ("Access-Control-Allow-Credentials", "true".to_string())
+        );
+
+        let open = CorsPolicy::new(config(&["*"], false)).unwrap();
+        assert_eq!(open.response_headers("https://anyone.test").unwrap(), [("Access-Control-Allow-Origin", "*".to_string())]);
+    }
+
+    #[test]
+    fn preflights_check_method_and_headers() {
+        let policy = CorsPolicy::new(config(&["https://app.test"], false)).unwrap();
+
+        assert_eq!(
+            policy.preflight_headers("https://app.test", "POST", "content-type").unwrap(),
+            [
+                ("Access-Control-Allow-Origin", "https://app.test".to_string()),
+                ("Access-Control-Allow-Methods", "GET, POST".to_string()),
+                ("Access-Control-Allow-Headers", "Content-Type".to_string()),
+                ("Access-Control-Max-Age", "600".to_string()),
+            ]
+        );
+        assert!(policy.preflight_headers("https://app.test", "DELETE", "").is_none());
+        assert!(policy.preflight_headers("https://app.test", "GET", "Content-Type, X-Token").is_none());
+        assert!(policy.preflight_headers("https://other.test", "GET", "").is_none());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cors;
+pub mod listen;
+pub mod rate_limit;
+pub mod security_headers;
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+/// Socket files are group-writable unless configured otherwise, so nginx can share a group.
+const DEFAULT_SOCKET_MODE: u32 = 0o660;
+
+/// First descriptor passed by the service manager, after stdin, stdout and stderr.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// An address to accept connections on.
+///
+/// Parses from `127.0.0.1:33030`, `[::]:33030` or `unix:/run/smn/site.sock`,
+/// optionally followed by `?mode=600` to set the socket file's permissions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    /// Unix only. A stale socket file left by a previous run is replaced.
+    Unix { path: PathBuf, mode: u32 },
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+
+        let Some(socket) = value.strip_prefix("unix:") else {
+            return value
+                .parse()
+                .map(Self::Tcp)
+                .map_err(|_| format!("bad listen address '{}'", value));
+        };
+
+        let (path, mode) = match socket.split_once("?mode=") {
+            Some((path, mode)) => {
+                let mode = u32::from_str_radix(mode, 8)
+                    .ok()
+                    .filter(|&mode| mode <= 0o777)
+                    .ok_or(format!("bad socket mode in '{}'", value))?;
+                (path, mode)
+            }
+            None => (socket, DEFAULT_SOCKET_MODE),
+        };
+
+        if path.is_empty() {
+            return Err(format!("missing socket path in '{}'", value));
+        }
+
+        Ok(Self::Unix {
+            path: PathBuf::from(path),
+            mode,
+        })
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "http://{}", addr),
+            Self::Unix { path, mode } => write!(f, "unix:{} (mode {:o})", path.display(), mode),
+        }
+    }
+}
+
+/// A bound or inherited listening socket, in blocking mode.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    pub fn bind(addr: &ListenAddr) -> io::Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => TcpListener::bind(addr).map(Self::Tcp),
+            #[cfg(unix)]
+            ListenAddr::Unix { path, mode } => {
+                use std::os::unix::fs::PermissionsExt;
+
+                remove_stale_socket(path)?;
+                let listener = UnixListener::bind(path)?;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(*mode))?;
+                Ok(Self::Unix(listener))
+            }
+            #[cfg(not(unix))]
+            ListenAddr::Unix { .. } => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix sockets are not available on this platform",
+            )),
+        }
+    }
+
+    /// Sockets handed over by systemd socket activation, so a restart never closes
+    /// the listening socket. `None` when the process was not activated.
+    #[cfg(unix)]
+    pub fn activated() -> Option<io::Result<Vec<Self>>> {
+        let count = activated_fd_count(
+            std::env::var("LISTEN_PID").ok().as_deref(),
+            std::env::var("LISTEN_FDS").ok().as_deref(),
+            std::process::id(),
+        )?;
+
+        Some((LISTEN_FDS_START..LISTEN_FDS_START + count).map(Self::from_fd).collect())
+    }
+
+    #[cfg(not(unix))]
+    pub fn activated() -> Option<io::Result<Vec<Self>>> {
+        None
+    }
+
+    /// The activated sockets, or else `listen` bound afresh. Inherited sockets that
+    /// cannot be taken over are reported and the configured addresses bound instead.
+    pub fn activated_or_bind(listen: &[ListenAddr]) -> io::Result<Vec<Self>> {
+        match Self::activated() {
+            Some(Ok(inherited)) => return Ok(inherited),
+            Some(Err(e)) => eprintln!("Cannot take over activated sockets ({}), binding instead", e),
+            None => {}
+        }
+
+        listen
+            .iter()
+            .map(|addr| Self::bind(addr).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", addr, e))))
+            .collect()
+    }
+
+    /// Takes ownership of an inherited descriptor, which may be a TCP or a Unix socket.
+    #[cfg(unix)]
+    fn from_fd(fd: i32) -> io::Result<Self> {
+        use std::os::fd::{FromRawFd, IntoRawFd};
+
+        // SAFETY: descriptors from LISTEN_FDS belong to this process and are taken once
+        let tcp = unsafe { TcpListener::from_raw_fd(fd) };
+        if tcp.local_addr().is_ok() {
+            return Ok(Self::Tcp(tcp));
+        }
+
+        // Not an IP socket, so try it as a Unix one
+        // SAFETY: ownership moves straight from `tcp` back into the new listener
+        let unix = unsafe { UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+        unix.local_addr()?;
+        Ok(Self::Unix(unix))
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "http://{}", addr),
+                Err(_) => write!(f, "a TCP socket"),
+            },
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let addr = listener.local_addr().ok();
+                match addr.as_ref().and_then(|addr| addr.as_pathname()) {
+                    Some(path) => write!(f, "unix:{}", path.display()),
+                    None => write!(f, "an unnamed Unix socket"),
+                }
+            }
+        }
+    }
+}
+
+/// How many sockets were passed in, when `LISTEN_PID` says they are meant for this
+/// process rather than a parent that exported the variables.
+#[cfg(unix)]
+fn activated_fd_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Option<i32> {
+    if listen_pid?.trim().parse::<u32>().ok()? != pid {
+        return None;
+    }
+
+    listen_fds?.trim().parse().ok().filter(|&count| count > 0)
+}
+
+/// Removes a socket file left behind by a previous run; any other file is left alone.
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tcp_and_unix_addresses() {
+        assert_eq!(
+            "[::1]:8080".parse::<ListenAddr>().unwrap(),
+            ListenAddr::Tcp("[::1]:8080".parse().unwrap())
+        );
+        assert_eq!(
+            " unix:/run/site.sock?mode=600 ".parse::<ListenAddr>().unwrap(),
+            ListenAddr::Unix {
+                path: PathBuf::from("/run/site.sock"),
+                mode: 0o600,
+            }
+        );
+        assert!(matches!(
+            "unix:/run/site.sock".parse(),
+            Ok(ListenAddr::Unix { mode: DEFAULT_SOCKET_MODE, .. })
+        ));
+        assert!("localhost:8080".parse::<ListenAddr>().is_err());
+        assert!("unix:/run/site.sock?mode=999".parse::<ListenAddr>().is_err());
+        assert!("unix:".parse::<ListenAddr>().is_err());
+        assert!("unix:?mode=600".parse::<ListenAddr>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn activation_needs_a_matching_pid() {
+        assert_eq!(activated_fd_count(Some("42"), Some("2"), 42), Some(2));
+        assert_eq!(activated_fd_count(Some(" 42\n"), Some("1"), 42), Some(1));
+        assert_eq!(activated_fd_count(Some("41"), Some("2"), 42), None);
+        assert_eq!(activated_fd_count(None, Some("2"), 42), None);
+        assert_eq!(activated_fd_count(Some("42"), None, 42), None);
+        assert_eq!(activated_fd_count(Some("42"), Some("0"), 42), None);
+        assert_eq!(activated_fd_count(Some("42"), Some("-1"), 42), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inherited_descriptors_keep_their_socket_kind() {
+        use std::os::fd::IntoRawFd;
+
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(matches!(Listener::from_fd(tcp.into_raw_fd()), Ok(Listener::Tcp(_))));
+
+        let path = std::env::temp_dir().join(format!("smn-shared-inherit-{}.sock", std::process::id()));
+        let unix = Listener::bind(&ListenAddr::Unix { path: path.clone(), mode: 0o600 }).unwrap();
+        let Listener::Unix(unix) = unix else { panic!("bound a Unix address as TCP") };
+        assert!(matches!(Listener::from_fd(unix.into_raw_fd()), Ok(Listener::Unix(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
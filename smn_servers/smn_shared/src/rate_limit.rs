@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Idle buckets are pruned once this many are being tracked.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone)]
+pub struct RateLimitRule {
+    pub path_prefix: String,
+    /// Requests a client may make in a burst.
+    pub burst: u32,
+    /// Tokens refilled per second once the burst is spent.
+    pub per_second: f64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets keyed by rule and client address. The longest matching prefix
+/// applies; paths matching no rule are unlimited.
+pub struct TokenBuckets {
+    rules: Vec<RateLimitRule>,
+    buckets: Mutex<HashMap<(usize, IpAddr), Bucket>>,
+}
+
+impl TokenBuckets {
+    pub fn new(rules: Vec<RateLimitRule>) -> Self {
+        Self {
+            rules,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for the client, or returns the seconds until one is available.
+    pub fn take(&self, path: &str, client: IpAddr) -> Result<(), u64> {
+        self.take_at(path, client, Instant::now())
+    }
+
+    fn take_at(&self, path: &str, client: IpAddr, now: Instant) -> Result<(), u64> {
+        let Some((index, rule)) = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| path.starts_with(&rule.path_prefix))
+            .max_by_key(|(_, rule)| rule.path_prefix.len())
+        else {
+            return Ok(());
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            self.prune(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry((index, client)).or_insert(Bucket {
+            tokens: rule.burst as f64,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rule.per_second).min(rule.burst as f64);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(((1.0 - bucket.tokens) / rule.per_second).ceil().max(1.0) as u64)
+    }
+
+    /// Drops buckets that would have refilled completely by now.
+    fn prune(&self, buckets: &mut HashMap<(usize, IpAddr), Bucket>, now: Instant) {
+        buckets.retain(|(index, _), bucket| {
+            let rule = &self.rules[*index];
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * rule.per_second < rule.burst as f64
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn buckets() -> TokenBuckets {
+        TokenBuckets::new(vec![
+            RateLimitRule {
+                path_prefix: "/".to_string(),
+                burst: 100,
+                per_second: 50.0,
+            },
+            RateLimitRule {
+                path_prefix: "/contact".to_string(),
+                burst: 2,
+                per_second: 0.5,
+            },
+        ])
+    }
+
+    fn client(last: u8) -> IpAddr {
+        IpAddr::from([203, 0, 113, last])
+    }
+
+    #[test]
+    fn burst_then_refill_under_the_longest_prefix() {
+        let buckets = buckets();
+        let start = Instant::now();
+
+        assert_eq!(buckets.take_at("/contact", client(1), start), Ok(()));
+        assert_eq!(buckets.take_at("/contact/send", client(1), start), Ok(()));
+        assert_eq!(buckets.take_at("/contact", client(1), start), Err(2));
+
+        // Other clients and other rules keep their own buckets
+        assert_eq!(buckets.take_at("/contact", client(2), start), Ok(()));
+        assert_eq!(buckets.take_at("/about", client(1), start), Ok(()));
+
+        assert_eq!(buckets.take_at("/contact", client(1), start + Duration::from_secs(1)), Err(1));
+        assert_eq!(buckets.take_at("/contact", client(1), start + Duration::from_secs(2)), Ok(()));
+    }
+
+    #[test]
+    fn unmatched_paths_are_unlimited() {
+        let buckets = TokenBuckets::new(vec![RateLimitRule {
+            path_prefix: "/api/".to_string(),
+            burst: 1,
+            per_second: 1.0,
+        }]);
+
+        for _ in 0..5 {
+            assert_eq!(buckets.take("/index.html", client(1)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn prune_drops_only_refilled_buckets() {
+        let buckets = buckets();
+        let start = Instant::now();
+        buckets.take_at("/contact", client(1), start).unwrap();
+        buckets.take_at("/about", client(2), start).unwrap();
+
+        let mut map = buckets.buckets.lock().unwrap();
+        buckets.prune(&mut map, start + Duration::from_millis(100));
+
+        assert_eq!(map.len(), 1);
+        assert!(map.contains_key(&(1, client(1))));
+    }
+}
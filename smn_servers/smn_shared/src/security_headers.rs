@@ -0,0 +1,179 @@
+/// The headers sent with a response; `None` leaves a header out.
+#[derive(Clone)]
+pub struct SecurityPolicy {
+    pub content_security_policy: Option<String>,
+    /// Sends the CSP as `Content-Security-Policy-Report-Only` so violations are reported, not blocked.
+    pub csp_report_only: bool,
+    /// Appended to the CSP as a `report-uri` directive.
+    pub csp_report_uri: Option<String>,
+    /// Adds `X-Content-Type-Options: nosniff`.
+    pub nosniff: bool,
+    pub frame_options: Option<String>,
+    pub referrer_policy: Option<String>,
+    pub permissions_policy: Option<String>,
+    /// Usually left to the TLS-terminating proxy; only sent for requests made over https.
+    pub strict_transport_security: Option<String>,
+}
+
+impl Default for SecurityPolicy {
+    fn default() -> Self {
+        Self {
+            content_security_policy: Some(
+                "default-src 'self'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'"
+                    .to_string(),
+            ),
+            csp_report_only: false,
+            csp_report_uri: None,
+            nosniff: true,
+            frame_options: Some("DENY".to_string()),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            permissions_policy: Some("camera=(), microphone=(), geolocation=()".to_string()),
+            strict_transport_security: None,
+        }
+    }
+}
+
+impl SecurityPolicy {
+    /// The headers this policy sends for a request, `https` saying how it arrived.
+    pub fn headers(&self, https: bool) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+
+        if let Some(csp) = &self.content_security_policy {
+            let name = if self.csp_report_only {
+                "Content-Security-Policy-Report-Only"
+            } else {
+                "Content-Security-Policy"
+            };
+
+            let value = match &self.csp_report_uri {
+                Some(uri) => format!("{}; report-uri {}", csp, uri),
+                None => csp.clone(),
+            };
+            headers.push((name, value));
+        }
+
+        if self.nosniff {
+            headers.push(("X-Content-Type-Options", "nosniff".to_string()));
+        }
+
+        let hsts = self.strict_transport_security.as_ref().filter(|_| https);
+        let optional = [
+            ("X-Frame-Options", self.frame_options.as_ref()),
+            ("Referrer-Policy", self.referrer_policy.as_ref()),
+            ("Permissions-Policy", self.permissions_policy.as_ref()),
+            ("Strict-Transport-Security", hsts),
+        ];
+
+        for (name, value) in optional {
+            if let Some(value) = value {
+                headers.push((name, value.clone()));
+            }
+        }
+
+        headers
+    }
+}
+
+/// Replaces the default policy for paths under `path_prefix`.
+pub struct SecurityOverride {
+    pub path_prefix: String,
+    pub policy: SecurityPolicy,
+}
+
+#[derive(Default)]
+pub struct SecurityConfig {
+    pub policy: SecurityPolicy,
+    /// The longest matching prefix applies.
+    pub overrides: Vec<SecurityOverride>,
+}
+
+impl SecurityConfig {
+    pub fn policy_for(&self, path: &str) -> &SecurityPolicy {
+        self.overrides
+            .iter()
+            .filter(|rule| path.starts_with(&rule.path_prefix))
+            .max_by_key(|rule| rule.path_prefix.len())
+            .map(|rule| &rule.policy)
+            .unwrap_or(&self.policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SecurityConfig {
+        let embeddable = SecurityPolicy {
+            content_security_policy: Some("default-src 'self'; frame-ancestors *".to_string()),
+            frame_options: None,
+            ..SecurityPolicy::default()
+        };
+        let reported = SecurityPolicy {
+            csp_report_only: true,
+            csp_report_uri: Some("/csp-report".to_string()),
+            ..embeddable.clone()
+        };
+
+        SecurityConfig {
+            policy: SecurityPolicy::default(),
+            overrides: vec![
+                SecurityOverride {
+                    path_prefix: "/embed/".to_string(),
+                    policy: embeddable,
+                },
+                SecurityOverride {
+                    path_prefix: "/embed/beta/".to_string(),
+                    policy: reported,
+                },
+            ],
+        }
+    }
+
+    fn header(path: &str, name: &str) -> Option<String> {
+        config()
+            .policy_for(path)
+            .headers(false)
+            .into_iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn longest_prefix_override_replaces_the_policy() {
+        assert_eq!(
+            header("/about.html", "Content-Security-Policy"),
+            SecurityPolicy::default().content_security_policy
+        );
+        assert_eq!(header("/about.html", "X-Frame-Options").as_deref(), Some("DENY"));
+
+        assert_eq!(
+            header("/embed/widget.html", "Content-Security-Policy").as_deref(),
+            Some("default-src 'self'; frame-ancestors *")
+        );
+        assert_eq!(header("/embed/widget.html", "X-Frame-Options"), None);
+        assert_eq!(header("/embed/widget.html", "X-Content-Type-Options").as_deref(), Some("nosniff"));
+
+        assert_eq!(header("/embed/beta/widget.html", "Content-Security-Policy"), None);
+        assert_eq!(
+            header("/embed/beta/widget.html", "Content-Security-Policy-Report-Only").as_deref(),
+            Some("default-src 'self'; frame-ancestors *; report-uri /csp-report")
+        );
+    }
+
+    #[test]
+    fn strict_transport_security_needs_https() {
+        let policy = SecurityPolicy {
+            strict_transport_security: Some("max-age=63072000".to_string()),
+            ..SecurityPolicy::default()
+        };
+        let hsts = |https| {
+            policy
+                .headers(https)
+                .into_iter()
+                .any(|(name, _)| name == "Strict-Transport-Security")
+        };
+
+        assert!(hsts(true));
+        assert!(!hsts(false));
+    }
+}
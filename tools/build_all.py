@@ -23,12 +23,15 @@ def copy_item(src: Path, dst: Path):
         shutil.copy2(src, dst / src.name)
 
 
-def build_project(proj_path: Path, copy_list, output_root: Path):
+def build_project(proj_path: Path, copy_list, features, output_root: Path):
     proj_name = proj_path.name
     print(f"\n=== Building {proj_name} ===")
 
     # Build release version
-    run("cargo build --release", cwd=proj_path)
+    cmd = "cargo build --release"
+    if features:
+        cmd += " --features " + ",".join(features)
+    run(cmd, cwd=proj_path)
 
     target_release = proj_path / "target" / "release"
     out_dir = output_root / proj_name
@@ -84,7 +87,8 @@ def main():
     for proj in config["projects"]:
         proj_path = Path(proj["path"]).resolve()
         copy_list = proj.get("copy", [])
-        build_project(proj_path, copy_list, output_root)
+        features = proj.get("features", [])
+        build_project(proj_path, copy_list, features, output_root)
 
 
 if __name__ == "__main__":
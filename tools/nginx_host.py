@@ -52,27 +52,36 @@ def run(cmd: str):
     subprocess.check_call(cmd, shell=True)
 
 
-def build_rule(subdomain: str, port: int, base: str) -> str:
+def upstream(server: dict) -> tuple[str, str]:
+    """Proxy target and a label for it; a "socket" path takes precedence over "port"."""
+    if "socket" in server:
+        return f"http://unix:{server['socket']}:", f"socket {server['socket']}"
+    return f"http://127.0.0.1:{server['port']}", f"port {server['port']}"
+
+
+def build_rule(subdomain: str, server: dict, base: str) -> str:
+    target, label = upstream(server)
+
     if subdomain == "":
         fqdn = base
         www_fqdn = f"www.{base}"
 
-        return f"""        # --- {fqdn} traffic to port {port} ---
+        return f"""        # --- {fqdn} traffic to {label} ---
         if ($host = "{fqdn}") {{
-            proxy_pass http://127.0.0.1:{port};
+            proxy_pass {target};
             break;
         }}
 
         if ($host = "{www_fqdn}") {{
-            proxy_pass http://127.0.0.1:{port};
+            proxy_pass {target};
             break;
         }}"""
 
     else:
         fqdn = f"{subdomain}.{base}"
-        return f"""        # --- {fqdn} traffic to port {port} ---
+        return f"""        # --- {fqdn} traffic to {label} ---
         if ($host = "{fqdn}") {{
-            proxy_pass http://127.0.0.1:{port};
+            proxy_pass {target};
             break;
         }}"""
 
@@ -138,7 +147,7 @@ def main():
 
     rule_blocks = []
     for s in cfg["servers"]:
-        rule_blocks.append(build_rule(s["subDomain"], s["port"], base))
+        rule_blocks.append(build_rule(s["subDomain"], s, base))
 
     rules_str = "\n\n".join(rule_blocks)
 
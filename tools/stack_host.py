@@ -1,10 +1,19 @@
 #!/usr/bin/env python3
+import http.client
 import json
+import socket
 import subprocess
 import os
+import sys
+import time
+import urllib.error
+import urllib.request
 from pathlib import Path
 import platform
 
+
+READY_TIMEOUT_SECONDS = 30
+
 def resolve_exec_path(raw_path: Path) -> Path:
     system = platform.system().lower()
 
@@ -17,6 +26,63 @@ def resolve_exec_path(raw_path: Path) -> Path:
     return raw_path
 
 
+class UnixHTTPConnection(http.client.HTTPConnection):
+    """HTTP over a Unix socket, for services listening on `unix:` addresses."""
+
+    def __init__(self, socket_path: str):
+        super().__init__("localhost", timeout=2)
+        self.socket_path = socket_path
+
+    def connect(self):
+        self.sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        self.sock.settimeout(self.timeout)
+        self.sock.connect(self.socket_path)
+
+
+def probe(target, path: str) -> bool:
+    """`target` is a TCP port on 127.0.0.1 or the path of a Unix socket."""
+    if isinstance(target, int):
+        try:
+            with urllib.request.urlopen(f"http://127.0.0.1:{target}{path}", timeout=2) as resp:
+                return resp.status == 200
+        except (urllib.error.URLError, OSError):
+            return False
+
+    conn = UnixHTTPConnection(target)
+    try:
+        conn.request("GET", path)
+        return conn.getresponse().status == 200
+    except (http.client.HTTPException, OSError):
+        return False
+    finally:
+        conn.close()
+
+
+def describe(target) -> str:
+    return f"port {target}" if isinstance(target, int) else f"unix:{target}"
+
+
+def wait_until_ready(name: str, target) -> bool:
+    """
+    Polls /readyz until it answers 200 or the timeout passes.
+    Returns False only if the service is not even alive (/healthz).
+    """
+    deadline = time.time() + READY_TIMEOUT_SECONDS
+
+    while time.time() < deadline:
+        if probe(target, "/readyz"):
+            print(f"  [READY] {name} ({describe(target)})")
+            return True
+        time.sleep(1)
+
+    if probe(target, "/healthz"):
+        print(f"  [WARN] {name} is alive but not ready ({describe(target)})")
+        return True
+
+    print(f"  [ERROR] {name} did not come up ({describe(target)})")
+    return False
+
+
 def main():
     script_dir = Path(__file__).resolve().parent
     config_file = script_dir / "host_stack.json"
@@ -33,6 +99,7 @@ def main():
 
     system = platform.system().lower()
     processes = []
+    started = []
 
     for svc in config["services"]:
         name = svc["name"]
@@ -67,6 +134,12 @@ def main():
 
         processes.append(proc)
 
+        # A service listening on a Unix socket names it with "socket" instead of "port"
+        if "socket" in svc:
+            started.append((name, svc["socket"]))
+        elif "port" in svc:
+            started.append((name, svc["port"]))
+
     # Write correct PIDs
     with open(pid_file, "w") as f:
         for p in processes:
@@ -74,6 +147,15 @@ def main():
 
     print("All services started.")
 
+    print("\nWaiting for services to report ready...")
+    failed = [name for name, target in started if not wait_until_ready(name, target)]
+
+    if failed:
+        print(f"[ERROR] Services failed health checks: {', '.join(failed)}")
+        sys.exit(1)
+
+    print("All services healthy.")
+
 
 if __name__ == "__main__":
     main()